@@ -0,0 +1,57 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use ssrkit::Cache;
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Barrier};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const KEYS: usize = 1000;
+
+fn populated_cache(shards: usize) -> Arc<Cache<String>> {
+    let cache = Cache::new(|_config| NonZeroUsize::new(KEYS).unwrap())
+        .with_shards(move |_config| NonZeroUsize::new(shards).unwrap());
+    // 鍵在分片間的分佈不完全均勻，預留一倍空間避免預熱時被淘汰
+    for i in 0..KEYS / 2 {
+        cache.insert(&format!("/page/{}", i), format!("<html>{}</html>", i));
+    }
+    Arc::new(cache)
+}
+
+// 多個線程同時讀取，返回所有線程完成 iters 次讀取的總耗時
+fn parallel_get(cache: &Arc<Cache<String>>, threads: usize, iters: u64) -> Duration {
+    let barrier = Arc::new(Barrier::new(threads + 1));
+    let handles: Vec<_> = (0..threads)
+        .map(|t| {
+            let cache = cache.clone();
+            let barrier = barrier.clone();
+            thread::spawn(move || {
+                barrier.wait();
+                for i in 0..iters as usize {
+                    let key = format!("/page/{}", (i * 31 + t * 17) % (KEYS / 2));
+                    criterion::black_box(cache.get(&key));
+                }
+            })
+        })
+        .collect();
+    barrier.wait();
+    let start = Instant::now();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    start.elapsed()
+}
+
+fn bench_parallel_readers(c: &mut Criterion) {
+    let threads = thread::available_parallelism().map_or(4, |n| n.get().max(2));
+    let mut group = c.benchmark_group(format!("cache_get_{}_threads", threads));
+    for shards in [1, 16] {
+        let cache = populated_cache(shards);
+        group.bench_with_input(BenchmarkId::new("shards", shards), &shards, |b, _| {
+            b.iter_custom(|iters| parallel_get(&cache, threads, iters))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_parallel_readers);
+criterion_main!(benches);
@@ -1,13 +1,138 @@
+use crate::clock::{Clock, SystemClock};
 use crate::config::SsrkitConfig;
+use chrono::{DateTime, Utc};
 use lru::LruCache;
+use std::collections::hash_map::RandomState;
+use std::collections::{HashMap, HashSet};
+use std::hash::BuildHasher;
 use std::num::NonZeroUsize;
-use std::sync::{Mutex, OnceLock};
+use std::sync::{Arc, Condvar, Mutex, OnceLock};
+use std::thread;
+use std::time::Duration;
 
 static CONFIG: OnceLock<SsrkitConfig> = OnceLock::new();
 
+struct CacheEntry<T> {
+    value: T,
+    // 寫入時使用的有效期，後台刷新時沿用
+    ttl: Option<Duration>,
+    expires_at: Option<DateTime<Utc>>,
+}
+
+type Shard<T> = Mutex<LruCache<String, CacheEntry<T>>>;
+type InFlight<T> = Mutex<HashMap<String, Arc<Flight<T>>>>;
+type ErrorCache = Mutex<LruCache<String, (String, DateTime<Utc>)>>;
+
+struct CacheInner<T> {
+    // 按鍵的哈希分片，每個分片獨立加鎖，默認只有一個分片
+    shards: Vec<Shard<T>>,
+    hasher: RandomState,
+    default_ttl: Option<Duration>,
+    // 正在後台刷新的鍵，避免同一條目重複刷新
+    refreshing: Mutex<HashSet<String>>,
+    // 正在生成的鍵，同一個鍵的並發調用者等待同一次計算；與條目使用相同的分片，不同分片的未命中互不阻塞
+    in_flight: Vec<InFlight<T>>,
+    // 開啟負緩存時記錄最近失敗的鍵及其錯誤，到期前直接返回錯誤；未開啟時為 None
+    errors: Option<ErrorCache>,
+}
+
+impl<T> CacheInner<T> {
+    fn shard_index(&self, key: &str) -> usize {
+        self.hasher.hash_one(key) as usize % self.shards.len()
+    }
+
+    fn shard(&self, key: &str) -> &Shard<T> {
+        &self.shards[self.shard_index(key)]
+    }
+
+    fn in_flight(&self, key: &str) -> &InFlight<T> {
+        &self.in_flight[self.shard_index(key)]
+    }
+
+    fn put(&self, key: &str, value: T, ttl: Option<Duration>, clock: &dyn Clock) {
+        let expires_at = expires_at(clock, ttl);
+        let mut cache_guard = self.shard(key).lock().unwrap();
+        cache_guard.put(
+            key.to_string(),
+            CacheEntry {
+                value,
+                ttl,
+                expires_at,
+            },
+        );
+        if let Some(errors) = &self.errors {
+            errors.lock().unwrap().pop(key);
+        }
+    }
+}
+
+// 刷新結束（包括 create_fn panic）時清除刷新標記
+struct RefreshGuard<T> {
+    inner: Arc<OnceLock<CacheInner<T>>>,
+    key: String,
+}
+
+impl<T> Drop for RefreshGuard<T> {
+    fn drop(&mut self) {
+        if let Some(inner) = self.inner.get() {
+            inner.refreshing.lock().unwrap().remove(&self.key);
+        }
+    }
+}
+
+enum FlightState<T> {
+    Pending,
+    Done(Result<T, String>),
+    // 負責計算的調用者 panic，等待者需要重新嘗試
+    Abandoned,
+}
+
+struct Flight<T> {
+    state: Mutex<FlightState<T>>,
+    ready: Condvar,
+}
+
+// 負責計算的調用者持有，結束（包括 panic）時移除在途標記並喚醒等待者
+struct FlightLeader<'a, T> {
+    in_flight: &'a InFlight<T>,
+    flight: Arc<Flight<T>>,
+    key: &'a str,
+}
+
+impl<T> Drop for FlightLeader<'_, T> {
+    fn drop(&mut self) {
+        self.in_flight.lock().unwrap().remove(self.key);
+        let mut state = self.flight.state.lock().unwrap();
+        if matches!(*state, FlightState::Pending) {
+            *state = FlightState::Abandoned;
+        }
+        self.flight.ready.notify_all();
+    }
+}
+
+enum Lookup<T> {
+    Fresh(T),
+    // 過期但仍在 max_stale 內的值及其原有效期
+    Stale(T, Option<Duration>),
+    Miss,
+}
+
+type CacheTtlFn = Box<dyn Fn(&SsrkitConfig) -> Option<Duration> + Send + Sync>;
+type CacheShardsFn = Box<dyn Fn(&SsrkitConfig) -> NonZeroUsize + Send + Sync>;
+
 pub struct Cache<T> {
-    cache: OnceLock<Mutex<LruCache<String, T>>>,
+    cache: Arc<OnceLock<CacheInner<T>>>,
     cache_size_fn: Box<dyn Fn(&SsrkitConfig) -> NonZeroUsize + Send + Sync>,
+    cache_ttl_fn: Option<CacheTtlFn>,
+    max_stale: Option<Duration>,
+    negative_ttl: Option<Duration>,
+    cache_shards_fn: Option<CacheShardsFn>,
+    clock: Arc<dyn Clock>,
+}
+
+fn expires_at(clock: &dyn Clock, ttl: Option<Duration>) -> Option<DateTime<Utc>> {
+    let ttl = chrono::Duration::from_std(ttl?).ok()?;
+    clock.now().checked_add_signed(ttl)
 }
 
 impl<T: Clone> Cache<T> {
@@ -15,39 +140,275 @@ impl<T: Clone> Cache<T> {
         cache_size_fn: impl Fn(&SsrkitConfig) -> NonZeroUsize + Send + Sync + 'static,
     ) -> Self {
         Self {
-            cache: OnceLock::new(),
+            cache: Arc::new(OnceLock::new()),
             cache_size_fn: Box::new(cache_size_fn),
+            cache_ttl_fn: None,
+            max_stale: None,
+            negative_ttl: None,
+            cache_shards_fn: None,
+            clock: Arc::new(SystemClock),
         }
     }
 
-    fn get_or_create_cache(&self) -> &Mutex<LruCache<String, T>> {
+    // 從配置中讀取默認有效期，在首次使用緩存時確定
+    pub fn with_ttl(
+        mut self,
+        cache_ttl_fn: impl Fn(&SsrkitConfig) -> Option<Duration> + Send + Sync + 'static,
+    ) -> Self {
+        self.cache_ttl_fn = Some(Box::new(cache_ttl_fn));
+        self
+    }
+
+    // 條目過期後的 max_stale 時間內仍可由 get_or_revalidate 返回，同時在後台刷新；
+    // get 和 get_or_insert 不受影響，仍把過期條目視為未命中
+    pub fn with_stale_while_revalidate(mut self, max_stale: Duration) -> Self {
+        self.max_stale = Some(max_stale);
+        self
+    }
+
+    // get_or_try_insert 的錯誤在 ttl 內被緩存，避免反覆失敗的鍵（如不存在的 island）一直觸發渲染
+    pub fn with_negative_ttl(mut self, ttl: Duration) -> Self {
+        self.negative_ttl = Some(ttl);
+        self
+    }
+
+    // 把容量平均分到多個獨立加鎖的 LRU 分片，減少多線程讀取時的鎖競爭
+    // LRU 淘汰只在分片內進行，因此整體淘汰順序是近似的；分片數同樣在首次使用緩存時從配置讀取
+    pub fn with_shards(
+        mut self,
+        cache_shards_fn: impl Fn(&SsrkitConfig) -> NonZeroUsize + Send + Sync + 'static,
+    ) -> Self {
+        self.cache_shards_fn = Some(Box::new(cache_shards_fn));
+        self
+    }
+
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    fn get_or_create_cache(&self) -> &CacheInner<T> {
         self.cache.get_or_init(|| {
             let config = CONFIG.get().cloned().unwrap_or_else(SsrkitConfig::default);
-            Mutex::new(LruCache::new((self.cache_size_fn)(&config)))
+            let size = (self.cache_size_fn)(&config);
+            let shard_count = self
+                .cache_shards_fn
+                .as_ref()
+                .map_or(NonZeroUsize::MIN, |f| f(&config))
+                .min(size);
+            // 餘數分給前面的分片，使總容量與配置的大小一致
+            let (base, remainder) = (
+                size.get() / shard_count.get(),
+                size.get() % shard_count.get(),
+            );
+            CacheInner {
+                shards: (0..shard_count.get())
+                    .map(|i| {
+                        let shard_size = base + usize::from(i < remainder);
+                        Mutex::new(LruCache::new(NonZeroUsize::new(shard_size).unwrap()))
+                    })
+                    .collect(),
+                hasher: RandomState::new(),
+                default_ttl: self.cache_ttl_fn.as_ref().and_then(|f| f(&config)),
+                refreshing: Mutex::new(HashSet::new()),
+                in_flight: (0..shard_count.get())
+                    .map(|_| Mutex::new(HashMap::new()))
+                    .collect(),
+                errors: self.negative_ttl.map(|_| Mutex::new(LruCache::new(size))),
+            }
         })
     }
 
     pub fn insert(&self, key: &str, value: T) -> T {
-        let mut cache_guard = self.get_or_create_cache().lock().unwrap();
-        cache_guard.put(key.to_string(), value.clone());
+        let ttl = self.get_or_create_cache().default_ttl;
+        self.put(key, value, ttl)
+    }
+
+    pub fn insert_with_ttl(&self, key: &str, value: T, ttl: Duration) -> T {
+        self.put(key, value, Some(ttl))
+    }
+
+    fn put(&self, key: &str, value: T, ttl: Option<Duration>) -> T {
+        self.get_or_create_cache()
+            .put(key, value.clone(), ttl, self.clock.as_ref());
         value
     }
 
+    // 超過 max_stale 的條目被移除，仍在容忍期內的保留給 get_or_revalidate
+    fn lookup(&self, key: &str) -> Lookup<T> {
+        let now = self.clock.now();
+        let mut cache_guard = self.get_or_create_cache().shard(key).lock().unwrap();
+        let Some(entry) = cache_guard.get(key) else {
+            return Lookup::Miss;
+        };
+        match entry.expires_at {
+            Some(expires_at) if expires_at <= now => {
+                let stale_until = self
+                    .max_stale
+                    .and_then(|max_stale| chrono::Duration::from_std(max_stale).ok())
+                    .and_then(|max_stale| expires_at.checked_add_signed(max_stale));
+                if stale_until.is_some_and(|stale_until| now < stale_until) {
+                    Lookup::Stale(entry.value.clone(), entry.ttl)
+                } else {
+                    cache_guard.pop(key);
+                    Lookup::Miss
+                }
+            }
+            _ => Lookup::Fresh(entry.value.clone()),
+        }
+    }
+
+    fn cached_error(&self, key: &str) -> Option<String> {
+        let mut errors = self.get_or_create_cache().errors.as_ref()?.lock().unwrap();
+        let (error, expires_at) = errors.get(key)?;
+        if *expires_at <= self.clock.now() {
+            errors.pop(key);
+            return None;
+        }
+        Some(error.clone())
+    }
+
+    // 已過期的條目視為未命中
     pub fn get(&self, key: &str) -> Option<T> {
-        let mut cache_guard = self.get_or_create_cache().lock().unwrap();
-        cache_guard.get(key).cloned()
+        match self.lookup(key) {
+            Lookup::Fresh(value) => Some(value),
+            Lookup::Stale(..) | Lookup::Miss => None,
+        }
     }
 
+    // 同一個鍵的並發調用只執行一次 create_fn，其餘調用者等待並共享結果
     pub fn get_or_insert<F>(&self, key: &str, create_fn: F) -> T
     where
         F: FnOnce() -> T,
     {
-        if let Some(value) = self.get(key) {
-            value
-        } else {
-            let new_value = create_fn();
-            self.insert(key, new_value)
+        let mut create_fn = Some(create_fn);
+        loop {
+            // 只有等待的是 get_or_try_insert 的失敗結果時才會出錯，此時 create_fn 尚未執行，重新嘗試即可
+            if let Ok(value) = self.single_flight(key, false, || Ok(create_fn.take().unwrap()())) {
+                return value;
+            }
+        }
+    }
+
+    // 同上，錯誤默認不緩存並返回給所有等待者；設置了 with_negative_ttl 時錯誤會被短暫緩存
+    pub fn get_or_try_insert<F>(&self, key: &str, create_fn: F) -> Result<T, String>
+    where
+        F: FnOnce() -> Result<T, String>,
+    {
+        self.single_flight(key, true, create_fn)
+    }
+
+    fn single_flight<F>(&self, key: &str, use_errors: bool, create_fn: F) -> Result<T, String>
+    where
+        F: FnOnce() -> Result<T, String>,
+    {
+        let cached = || {
+            if let Some(value) = self.get(key) {
+                return Some(Ok(value));
+            }
+            use_errors
+                .then(|| self.cached_error(key).map(Err))
+                .flatten()
+        };
+        if let Some(result) = cached() {
+            return result;
+        }
+        let inner = self.get_or_create_cache();
+        let in_flight = inner.in_flight(key);
+        loop {
+            let (flight, leader) = {
+                let mut in_flight = in_flight.lock().unwrap();
+                // 持有在途鎖時再次檢查，避免錯過剛完成的計算
+                if let Some(result) = cached() {
+                    return result;
+                }
+                match in_flight.get(key) {
+                    Some(flight) => (flight.clone(), false),
+                    None => {
+                        let flight = Arc::new(Flight {
+                            state: Mutex::new(FlightState::Pending),
+                            ready: Condvar::new(),
+                        });
+                        in_flight.insert(key.to_string(), flight.clone());
+                        (flight, true)
+                    }
+                }
+            };
+
+            if leader {
+                let _leader = FlightLeader {
+                    in_flight,
+                    flight: flight.clone(),
+                    key,
+                };
+                let result = create_fn();
+                match &result {
+                    Ok(value) => {
+                        self.insert(key, value.clone());
+                    }
+                    Err(error) => {
+                        if let (Some(errors), Some(expires_at)) = (
+                            &inner.errors,
+                            expires_at(self.clock.as_ref(), self.negative_ttl),
+                        ) {
+                            errors
+                                .lock()
+                                .unwrap()
+                                .put(key.to_string(), (error.clone(), expires_at));
+                        }
+                    }
+                }
+                *flight.state.lock().unwrap() = FlightState::Done(result.clone());
+                return result;
+            }
+
+            let mut state = flight.state.lock().unwrap();
+            while matches!(*state, FlightState::Pending) {
+                state = flight.ready.wait(state).unwrap();
+            }
+            if let FlightState::Done(result) = &*state {
+                return result.clone();
+            }
+        }
+    }
+}
+
+impl<T: Clone + Send + 'static> Cache<T> {
+    // 與 get_or_insert 相同，但過期不超過 max_stale 的條目會立即返回，並在後台線程中重新生成
+    pub fn get_or_revalidate<F>(&self, key: &str, create_fn: F) -> T
+    where
+        F: FnOnce() -> T + Send + 'static,
+    {
+        match self.lookup(key) {
+            Lookup::Fresh(value) => value,
+            Lookup::Stale(value, ttl) => {
+                self.schedule_refresh(key, ttl, create_fn);
+                value
+            }
+            Lookup::Miss => self.get_or_insert(key, create_fn),
+        }
+    }
+
+    // 刷新後的條目沿用原來的有效期，insert_with_ttl 寫入的條目不會變成默認有效期
+    fn schedule_refresh<F>(&self, key: &str, ttl: Option<Duration>, create_fn: F)
+    where
+        F: FnOnce() -> T + Send + 'static,
+    {
+        let inner = self.get_or_create_cache();
+        if !inner.refreshing.lock().unwrap().insert(key.to_string()) {
+            return;
         }
+        let clock = self.clock.clone();
+        let guard = RefreshGuard {
+            inner: self.cache.clone(),
+            key: key.to_string(),
+        };
+        thread::spawn(move || {
+            let value = create_fn();
+            if let Some(inner) = guard.inner.get() {
+                inner.put(&guard.key, value, ttl, clock.as_ref());
+            }
+        });
     }
 }
 
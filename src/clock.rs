@@ -0,0 +1,53 @@
+use chrono::{DateTime, Utc};
+use std::sync::Mutex;
+
+// 時間來源，Session、Cookie 和緩存過期都從這裡取當前時間，測試時可替換為手動時鐘
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+// 只有調用 advance 或 set 時才會前進的時鐘
+pub struct ManualClock {
+    now: Mutex<DateTime<Utc>>,
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new(Utc::now())
+    }
+}
+
+impl ManualClock {
+    pub fn new(start: DateTime<Utc>) -> Self {
+        Self {
+            now: Mutex::new(start),
+        }
+    }
+
+    pub fn advance(&self, duration: std::time::Duration) {
+        let mut now = self.now.lock().unwrap();
+        let delta = chrono::Duration::from_std(duration).unwrap_or(chrono::Duration::MAX);
+        *now = now
+            .checked_add_signed(delta)
+            .unwrap_or(DateTime::<Utc>::MAX_UTC);
+    }
+
+    pub fn set(&self, time: DateTime<Utc>) {
+        *self.now.lock().unwrap() = time;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.now.lock().unwrap()
+    }
+}
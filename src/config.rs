@@ -1,3 +1,4 @@
+use crate::session_store::SessionStoreKind;
 use std::num::NonZeroUsize;
 use std::sync::OnceLock;
 use std::time::Duration;
@@ -6,10 +7,28 @@ pub struct SsrkitConfig {
     pub nanoid_length: Option<usize>,
     pub nanoid_alphabet: Option<Vec<char>>,
     pub global_state_session_duration: Option<Duration>,
+    pub session_store: Option<SessionStoreKind>,
+    pub session_idle_timeout: Option<Duration>,
+    pub session_absolute_timeout: Option<Duration>,
+    pub session_cleanup_interval: Option<Duration>,
+    pub session_max_per_user: Option<usize>,
+    pub session_cookie_name: Option<String>,
+    pub session_cookie_secure: Option<bool>,
+    pub csrf_protection: Option<bool>,
     pub global_state_cache_size: Option<NonZeroUsize>,
+    pub global_state_cache_ttl: Option<Duration>,
+    pub global_state_cache_shards: Option<NonZeroUsize>,
     pub template_cache_size: Option<NonZeroUsize>,
+    pub template_cache_ttl: Option<Duration>,
+    pub template_cache_shards: Option<NonZeroUsize>,
+    pub cookie_signing_keys: Option<Vec<Vec<u8>>>,
+    pub cookie_encryption_keys: Option<Vec<Vec<u8>>>,
     #[cfg(feature = "island")]
     pub island_cache_size: Option<NonZeroUsize>,
+    #[cfg(feature = "island")]
+    pub island_cache_ttl: Option<Duration>,
+    #[cfg(feature = "island")]
+    pub island_cache_shards: Option<NonZeroUsize>,
 }
 
 impl SsrkitConfig {
@@ -40,21 +59,101 @@ impl SsrkitConfig {
             .unwrap_or(NonZeroUsize::new(1000).unwrap())
     }
 
+    // 緩存條目的默認有效期，None 表示只按容量淘汰
+    pub fn get_global_state_cache_ttl(&self) -> Option<Duration> {
+        self.global_state_cache_ttl
+    }
+
+    // 緩存的分片數，多線程讀取較多時增大以減少鎖競爭，默認不分片
+    pub fn get_global_state_cache_shards(&self) -> NonZeroUsize {
+        self.global_state_cache_shards.unwrap_or(NonZeroUsize::MIN)
+    }
+
     pub fn get_global_state_session_duration(&self) -> Duration {
         self.global_state_session_duration
             .unwrap_or(Duration::from_secs(3600))
     }
 
+    pub fn get_session_store(&self) -> SessionStoreKind {
+        self.session_store
+            .clone()
+            .unwrap_or(SessionStoreKind::Memory)
+    }
+
+    // 閒置超時，未設置時沿用 global_state_session_duration
+    pub fn get_session_idle_timeout(&self) -> Duration {
+        self.session_idle_timeout
+            .unwrap_or_else(|| self.get_global_state_session_duration())
+    }
+
+    // 從創建起計算的絕對有效期，None 表示不限制
+    pub fn get_session_absolute_timeout(&self) -> Option<Duration> {
+        self.session_absolute_timeout
+    }
+
+    // 後台清理過期 Session 的間隔，None 表示不啟動後台清理
+    pub fn get_session_cleanup_interval(&self) -> Option<Duration> {
+        self.session_cleanup_interval
+    }
+
+    // 每個用戶同時存在的 Session 上限，超出時淘汰最早創建的，None 表示不限制
+    pub fn get_session_max_per_user(&self) -> Option<usize> {
+        self.session_max_per_user
+    }
+
+    pub fn get_session_cookie_name(&self) -> String {
+        self.session_cookie_name
+            .clone()
+            .unwrap_or_else(|| "ssrkit_session".to_string())
+    }
+
+    pub fn get_session_cookie_secure(&self) -> bool {
+        self.session_cookie_secure.unwrap_or(false)
+    }
+
+    // 開啟後每次渲染都確保 Session 中有 CSRF 令牌並注入到 props
+    pub fn get_csrf_protection(&self) -> bool {
+        self.csrf_protection.unwrap_or(false)
+    }
+
     pub fn get_template_cache_size(&self) -> NonZeroUsize {
         self.template_cache_size
             .unwrap_or(NonZeroUsize::new(100).unwrap())
     }
 
+    pub fn get_template_cache_ttl(&self) -> Option<Duration> {
+        self.template_cache_ttl
+    }
+
+    pub fn get_template_cache_shards(&self) -> NonZeroUsize {
+        self.template_cache_shards.unwrap_or(NonZeroUsize::MIN)
+    }
+
+    // 第一個密鑰用於簽名，其餘密鑰僅用於驗證，以便輪換
+    pub fn get_cookie_signing_keys(&self) -> Vec<Vec<u8>> {
+        self.cookie_signing_keys.clone().unwrap_or_default()
+    }
+
+    // 同上，第一個密鑰用於加密，所有密鑰都會嘗試解密
+    pub fn get_cookie_encryption_keys(&self) -> Vec<Vec<u8>> {
+        self.cookie_encryption_keys.clone().unwrap_or_default()
+    }
+
     #[cfg(feature = "island")]
     pub fn get_island_cache_size(&self) -> NonZeroUsize {
         self.island_cache_size
             .unwrap_or(NonZeroUsize::new(100).unwrap())
     }
+
+    #[cfg(feature = "island")]
+    pub fn get_island_cache_ttl(&self) -> Option<Duration> {
+        self.island_cache_ttl
+    }
+
+    #[cfg(feature = "island")]
+    pub fn get_island_cache_shards(&self) -> NonZeroUsize {
+        self.island_cache_shards.unwrap_or(NonZeroUsize::MIN)
+    }
 }
 
 impl Default for SsrkitConfig {
@@ -69,10 +168,28 @@ impl Default for SsrkitConfig {
                     .collect(),
             ),
             global_state_session_duration: Some(Duration::from_secs(3600)),
+            session_store: Some(SessionStoreKind::Memory),
+            session_idle_timeout: None,
+            session_absolute_timeout: None,
+            session_cleanup_interval: None,
+            session_max_per_user: None,
+            session_cookie_name: Some("ssrkit_session".to_string()),
+            session_cookie_secure: Some(false),
+            csrf_protection: Some(false),
             global_state_cache_size: Some(NonZeroUsize::new(1000).unwrap()),
+            global_state_cache_ttl: None,
+            global_state_cache_shards: Some(NonZeroUsize::MIN),
             template_cache_size: Some(NonZeroUsize::new(100).unwrap()),
+            template_cache_ttl: None,
+            template_cache_shards: Some(NonZeroUsize::MIN),
+            cookie_signing_keys: None,
+            cookie_encryption_keys: None,
             #[cfg(feature = "island")]
             island_cache_size: Some(NonZeroUsize::new(100).unwrap()),
+            #[cfg(feature = "island")]
+            island_cache_ttl: None,
+            #[cfg(feature = "island")]
+            island_cache_shards: Some(NonZeroUsize::MIN),
         }
     }
 }
@@ -83,10 +200,28 @@ impl Clone for SsrkitConfig {
             nanoid_length: self.nanoid_length,
             nanoid_alphabet: self.nanoid_alphabet.clone(),
             global_state_session_duration: self.global_state_session_duration,
+            session_store: self.session_store.clone(),
+            session_idle_timeout: self.session_idle_timeout,
+            session_absolute_timeout: self.session_absolute_timeout,
+            session_cleanup_interval: self.session_cleanup_interval,
+            session_max_per_user: self.session_max_per_user,
+            session_cookie_name: self.session_cookie_name.clone(),
+            session_cookie_secure: self.session_cookie_secure,
+            csrf_protection: self.csrf_protection,
             global_state_cache_size: self.global_state_cache_size,
+            global_state_cache_ttl: self.global_state_cache_ttl,
+            global_state_cache_shards: self.global_state_cache_shards,
             template_cache_size: self.template_cache_size,
+            template_cache_ttl: self.template_cache_ttl,
+            template_cache_shards: self.template_cache_shards,
+            cookie_signing_keys: self.cookie_signing_keys.clone(),
+            cookie_encryption_keys: self.cookie_encryption_keys.clone(),
             #[cfg(feature = "island")]
             island_cache_size: self.island_cache_size,
+            #[cfg(feature = "island")]
+            island_cache_ttl: self.island_cache_ttl,
+            #[cfg(feature = "island")]
+            island_cache_shards: self.island_cache_shards,
         }
     }
 }
@@ -95,10 +230,28 @@ pub struct SsrkitConfigChanger {
     nanoid_length: Option<usize>,
     nanoid_alphabet: Option<Vec<char>>,
     global_state_session_duration: Option<Duration>,
+    session_store: Option<SessionStoreKind>,
+    session_idle_timeout: Option<Duration>,
+    session_absolute_timeout: Option<Duration>,
+    session_cleanup_interval: Option<Duration>,
+    session_max_per_user: Option<usize>,
+    session_cookie_name: Option<String>,
+    session_cookie_secure: Option<bool>,
+    csrf_protection: Option<bool>,
     global_state_cache_size: Option<NonZeroUsize>,
+    global_state_cache_ttl: Option<Duration>,
+    global_state_cache_shards: Option<NonZeroUsize>,
     template_cache_size: Option<NonZeroUsize>,
+    template_cache_ttl: Option<Duration>,
+    template_cache_shards: Option<NonZeroUsize>,
+    cookie_signing_keys: Option<Vec<Vec<u8>>>,
+    cookie_encryption_keys: Option<Vec<Vec<u8>>>,
     #[cfg(feature = "island")]
     island_cache_size: Option<NonZeroUsize>,
+    #[cfg(feature = "island")]
+    island_cache_ttl: Option<Duration>,
+    #[cfg(feature = "island")]
+    island_cache_shards: Option<NonZeroUsize>,
 }
 
 impl SsrkitConfigChanger {
@@ -107,10 +260,28 @@ impl SsrkitConfigChanger {
             nanoid_length: None,
             nanoid_alphabet: None,
             global_state_session_duration: None,
+            session_store: None,
+            session_idle_timeout: None,
+            session_absolute_timeout: None,
+            session_cleanup_interval: None,
+            session_max_per_user: None,
+            session_cookie_name: None,
+            session_cookie_secure: None,
+            csrf_protection: None,
             global_state_cache_size: None,
+            global_state_cache_ttl: None,
+            global_state_cache_shards: None,
             template_cache_size: None,
+            template_cache_ttl: None,
+            template_cache_shards: None,
+            cookie_signing_keys: None,
+            cookie_encryption_keys: None,
             #[cfg(feature = "island")]
             island_cache_size: None,
+            #[cfg(feature = "island")]
+            island_cache_ttl: None,
+            #[cfg(feature = "island")]
+            island_cache_shards: None,
         }
     }
 
@@ -129,31 +300,131 @@ impl SsrkitConfigChanger {
         self
     }
 
+    pub fn global_state_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.global_state_cache_ttl = Some(ttl);
+        self
+    }
+
+    pub fn global_state_cache_shards(mut self, shards: NonZeroUsize) -> Self {
+        self.global_state_cache_shards = Some(shards);
+        self
+    }
+
     pub fn global_state_session_duration(mut self, duration: Duration) -> Self {
         self.global_state_session_duration = Some(duration);
         self
     }
 
+    pub fn session_store(mut self, store: SessionStoreKind) -> Self {
+        self.session_store = Some(store);
+        self
+    }
+
+    pub fn session_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.session_idle_timeout = Some(timeout);
+        self
+    }
+
+    pub fn session_absolute_timeout(mut self, timeout: Duration) -> Self {
+        self.session_absolute_timeout = Some(timeout);
+        self
+    }
+
+    pub fn session_cleanup_interval(mut self, interval: Duration) -> Self {
+        self.session_cleanup_interval = Some(interval);
+        self
+    }
+
+    pub fn session_max_per_user(mut self, max: usize) -> Self {
+        self.session_max_per_user = Some(max);
+        self
+    }
+
+    pub fn session_cookie_name(mut self, name: &str) -> Self {
+        self.session_cookie_name = Some(name.to_string());
+        self
+    }
+
+    pub fn session_cookie_secure(mut self, secure: bool) -> Self {
+        self.session_cookie_secure = Some(secure);
+        self
+    }
+
+    pub fn csrf_protection(mut self, enabled: bool) -> Self {
+        self.csrf_protection = Some(enabled);
+        self
+    }
+
     pub fn template_cache_size(mut self, size: NonZeroUsize) -> Self {
         self.template_cache_size = Some(size);
         self
     }
 
+    pub fn template_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.template_cache_ttl = Some(ttl);
+        self
+    }
+
+    pub fn template_cache_shards(mut self, shards: NonZeroUsize) -> Self {
+        self.template_cache_shards = Some(shards);
+        self
+    }
+
+    pub fn cookie_signing_keys(mut self, keys: Vec<Vec<u8>>) -> Self {
+        self.cookie_signing_keys = Some(keys);
+        self
+    }
+
+    pub fn cookie_encryption_keys(mut self, keys: Vec<Vec<u8>>) -> Self {
+        self.cookie_encryption_keys = Some(keys);
+        self
+    }
+
     #[cfg(feature = "island")]
     pub fn island_cache_size(mut self, size: NonZeroUsize) -> Self {
         self.island_cache_size = Some(size);
         self
     }
 
+    #[cfg(feature = "island")]
+    pub fn island_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.island_cache_ttl = Some(ttl);
+        self
+    }
+
+    #[cfg(feature = "island")]
+    pub fn island_cache_shards(mut self, shards: NonZeroUsize) -> Self {
+        self.island_cache_shards = Some(shards);
+        self
+    }
+
     pub fn finish(self) -> SsrkitConfig {
         SsrkitConfig {
             nanoid_length: self.nanoid_length,
             nanoid_alphabet: self.nanoid_alphabet,
             global_state_session_duration: self.global_state_session_duration,
+            session_store: self.session_store,
+            session_idle_timeout: self.session_idle_timeout,
+            session_absolute_timeout: self.session_absolute_timeout,
+            session_cleanup_interval: self.session_cleanup_interval,
+            session_max_per_user: self.session_max_per_user,
+            session_cookie_name: self.session_cookie_name,
+            session_cookie_secure: self.session_cookie_secure,
+            csrf_protection: self.csrf_protection,
             global_state_cache_size: self.global_state_cache_size,
+            global_state_cache_ttl: self.global_state_cache_ttl,
+            global_state_cache_shards: self.global_state_cache_shards,
             template_cache_size: self.template_cache_size,
+            template_cache_ttl: self.template_cache_ttl,
+            template_cache_shards: self.template_cache_shards,
+            cookie_signing_keys: self.cookie_signing_keys,
+            cookie_encryption_keys: self.cookie_encryption_keys,
             #[cfg(feature = "island")]
             island_cache_size: self.island_cache_size,
+            #[cfg(feature = "island")]
+            island_cache_ttl: self.island_cache_ttl,
+            #[cfg(feature = "island")]
+            island_cache_shards: self.island_cache_shards,
         }
     }
 }
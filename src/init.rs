@@ -51,6 +51,16 @@ impl SsrInitializer {
     }
 
     pub fn init(self) {
+        if let Err(e) = self.try_init() {
+            panic!("Failed to initialize ssrkit: {}", e);
+        }
+    }
+
+    pub fn try_init(self) -> Result<(), String> {
+        // 先構建並校驗 ParamsProcessor，避免路由衝突到運行時才暴露
+        let params_processor = (self.params_processor_init)();
+        params_processor.validate()?;
+
         let config = self.config.unwrap_or_else(|| get_global_config().clone());
 
         // 設置全局配置
@@ -60,9 +70,11 @@ impl SsrInitializer {
         crate::cache::init_cache(&config);
 
         // 初始化 GlobalState
-        let cache = Cache::new(|config| config.get_global_state_cache_size());
-        let session_duration = config.get_global_state_session_duration();
-        init_global_state(cache, config.clone(), session_duration);
+        let cache = Cache::new(|config| config.get_global_state_cache_size())
+            .with_ttl(|config| config.get_global_state_cache_ttl())
+            .with_shards(|config| config.get_global_state_cache_shards());
+        let session_duration = config.get_session_idle_timeout();
+        init_global_state(cache, config.clone(), session_duration)?;
 
         #[cfg(feature = "island")]
         // 初始化正則表達式
@@ -86,12 +98,14 @@ impl SsrInitializer {
         // 初始化 Renderer
         RENDERER.get_or_init(|| {
             SsrRenderer::new(
-                (self.params_processor_init)(),
+                params_processor,
                 #[cfg(feature = "island")]
                 ISLAND_MANAGER.get().unwrap().clone(),
                 TEMPLATE.get().unwrap().clone(),
             )
         });
+
+        Ok(())
     }
 }
 
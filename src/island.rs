@@ -1,4 +1,5 @@
 use crate::config::get_global_config;
+use crate::state::global_clock;
 use crate::{Cache, SsrkitConfig};
 use nanoid::nanoid;
 use serde_json::Value;
@@ -39,7 +40,7 @@ impl IslandManager {
         }
     }
 
-    pub fn register(&self) -> IslandRegistration {
+    pub fn register(&self) -> IslandRegistration<'_> {
         IslandRegistration::new(self)
     }
 
@@ -131,8 +132,14 @@ impl Clone for IslandManager {
     }
 }
 
+// 與模板緩存相同，使用全局狀態的時鐘
 pub fn init_island_cache() {
-    ISLAND_CACHE.get_or_init(|| Cache::new(|config| config.get_island_cache_size()));
+    ISLAND_CACHE.get_or_init(|| {
+        Cache::new(|config| config.get_island_cache_size())
+            .with_ttl(|config| config.get_island_cache_ttl())
+            .with_shards(|config| config.get_island_cache_shards())
+            .with_clock(global_clock())
+    });
 }
 
 pub fn get_or_render_island<F>(key: &str, render_fn: F) -> String
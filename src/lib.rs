@@ -2,10 +2,12 @@
 pub mod island;
 
 pub mod cache;
+pub mod clock;
 pub mod config;
 pub mod init;
 pub mod params;
 pub mod render;
+pub mod session_store;
 pub mod state;
 pub mod template;
 
@@ -16,13 +18,16 @@ pub use island::{
 };
 
 pub use cache::{init_cache, Cache};
+pub use clock::{Clock, ManualClock, SystemClock};
 pub use config::{get_global_config, set_global_config, SsrkitConfig};
 pub use init::SsrInitializer;
-pub use params::{CombinedParamsProcessor, ParamsProcessor};
+pub use params::{CombinedParamsProcessor, ParamsProcessor, RoutePattern};
 pub use render::{get_renderer, SsrRenderer};
+pub use session_store::{FileSessionStore, MemorySessionStore, SessionStore, SessionStoreKind};
 pub use state::{
-    get_global_state, init_global_state, Cookie, CookieManager, GlobalState, Session,
-    SessionManager,
+    get_global_state, init_global_state, Cookie, CookieManager, FlashMessage, GlobalState,
+    PrivateCookieManager, RequestSession, SameSite, Session, SessionEvent, SessionManager,
+    SessionReaper, SessionRef,
 };
 pub use template::Template;
 
@@ -42,13 +47,18 @@ pub mod prelude {
     };
 
     pub use crate::cache::{init_cache, Cache};
+    pub use crate::clock::{Clock, ManualClock, SystemClock};
     pub use crate::config::{get_global_config, set_global_config, SsrkitConfig};
     pub use crate::init::SsrInitializer;
-    pub use crate::params::{CombinedParamsProcessor, ParamsProcessor};
+    pub use crate::params::{CombinedParamsProcessor, ParamsProcessor, RoutePattern};
     pub use crate::render::{get_renderer, SsrRenderer};
+    pub use crate::session_store::{
+        FileSessionStore, MemorySessionStore, SessionStore, SessionStoreKind,
+    };
     pub use crate::state::{
-        get_global_state, init_global_state, Cookie, CookieManager, GlobalState, Session,
-        SessionManager,
+        get_global_state, init_global_state, Cookie, CookieManager, FlashMessage, GlobalState,
+        PrivateCookieManager, RequestSession, SameSite, Session, SessionEvent, SessionManager,
+        SessionReaper, SessionRef,
     };
     pub use crate::template::Template;
 
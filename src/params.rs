@@ -1,5 +1,5 @@
 use serde_json::Value;
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
 
 pub trait ParamsProcessor: Send + Sync {
     fn process(
@@ -7,10 +7,333 @@ pub trait ParamsProcessor: Send + Sync {
         path: &str,
         params: &HashMap<String, String>,
     ) -> serde_json::Map<String, Value>;
+
+    fn validate(&self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Static(String),
+    // [id]
+    Dynamic(String),
+    // [[lang]]
+    OptionalDynamic(String),
+    // [...slug]
+    CatchAll(String),
+    // [[...slug]]
+    OptionalCatchAll(String),
+}
+
+impl Segment {
+    fn parse(raw: &str) -> Self {
+        if let Some(inner) = raw.strip_prefix("[[").and_then(|s| s.strip_suffix("]]")) {
+            match inner.strip_prefix("...") {
+                Some(name) if !name.is_empty() => Segment::OptionalCatchAll(name.to_string()),
+                None if !inner.is_empty() => Segment::OptionalDynamic(inner.to_string()),
+                _ => Segment::Static(raw.to_string()),
+            }
+        } else if let Some(inner) = raw.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+            match inner.strip_prefix("...") {
+                Some(name) if !name.is_empty() => Segment::CatchAll(name.to_string()),
+                None if !inner.is_empty() => Segment::Dynamic(inner.to_string()),
+                _ => Segment::Static(raw.to_string()),
+            }
+        } else {
+            Segment::Static(raw.to_string())
+        }
+    }
+
+    // 靜態段 > 動態段 > 可選動態段 > 捕獲全部 > 可選捕獲全部
+    fn rank(&self) -> u8 {
+        match self {
+            Segment::Static(_) => 5,
+            Segment::Dynamic(_) => 4,
+            Segment::OptionalDynamic(_) => 3,
+            Segment::CatchAll(_) => 2,
+            Segment::OptionalCatchAll(_) => 1,
+        }
+    }
+
+    fn name(&self) -> Option<&str> {
+        match self {
+            Segment::Static(_) => None,
+            Segment::Dynamic(name)
+            | Segment::OptionalDynamic(name)
+            | Segment::CatchAll(name)
+            | Segment::OptionalCatchAll(name) => Some(name),
+        }
+    }
+}
+
+// 路由模式，如 `/blog/[id]`、`/docs/[...slug]`、`/[[lang]]/about`
+// 模式需匹配完整的請求路徑，只有捕獲全部段才會匹配剩餘的多個路徑段
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoutePattern {
+    pattern: String,
+    segments: Vec<Segment>,
+}
+
+impl RoutePattern {
+    pub fn new(pattern: &str) -> Self {
+        Self {
+            pattern: pattern.to_string(),
+            segments: split_path(pattern).map(Segment::parse).collect(),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+
+    // 逐段比較的特異性，值越大越優先；較長的模式優先於其前綴
+    fn specificity(&self) -> Vec<u8> {
+        self.segments.iter().map(Segment::rank).collect()
+    }
+
+    // 以下把模式看作按路徑段匹配的非確定自動機，狀態 i 表示已匹配前 i 個模式段，
+    // 路由衝突按狀態對逐段檢查，不需要列舉具體路徑
+
+    // 不消耗路徑段即可到達的狀態（跳過可選段）
+    fn closure(&self, states: impl IntoIterator<Item = usize>) -> BTreeSet<usize> {
+        let mut result = BTreeSet::new();
+        let mut pending: Vec<usize> = states.into_iter().collect();
+        while let Some(state) = pending.pop() {
+            if !result.insert(state) {
+                continue;
+            }
+            if matches!(
+                self.segments.get(state),
+                Some(Segment::OptionalDynamic(_) | Segment::OptionalCatchAll(_))
+            ) {
+                pending.push(state + 1);
+            }
+        }
+        result
+    }
+
+    // 從狀態消耗一個路徑段後的狀態，token 為 None 表示不等於任何靜態段的值
+    fn step(&self, state: usize, token: Option<&str>) -> Vec<usize> {
+        match self.segments.get(state) {
+            None => Vec::new(),
+            Some(Segment::Static(literal)) if token == Some(literal.as_str()) => vec![state + 1],
+            Some(Segment::Static(_)) => Vec::new(),
+            Some(Segment::Dynamic(_) | Segment::OptionalDynamic(_)) => vec![state + 1],
+            // 捕獲全部段可以繼續捕獲，也可以結束
+            Some(Segment::CatchAll(_) | Segment::OptionalCatchAll(_)) => vec![state, state + 1],
+        }
+    }
+
+    // 檢查排在前面的 self 是否讓 lower 無法到達，或在兩者都匹配的路徑上搶走了
+    // 在某個路徑段更具體的 lower
+    fn conflict_with(&self, lower: &RoutePattern) -> Option<String> {
+        // 兩個模式的靜態段加上「其它值」即可覆蓋所有路徑段的匹配情況
+        let mut alphabet: Vec<Option<&str>> = self
+            .segments
+            .iter()
+            .chain(&lower.segments)
+            .filter_map(|segment| match segment {
+                Segment::Static(literal) => Some(Some(literal.as_str())),
+                _ => None,
+            })
+            .collect();
+        alphabet.sort();
+        alphabet.dedup();
+        alphabet.push(None);
+
+        if let Some(path) = self.find_precedence_overlap(lower, &alphabet) {
+            return Some(format!(
+                "Routes '{}' and '{}' both match '/{}', which resolves to the less specific '{}'",
+                self.pattern,
+                lower.pattern,
+                path.join("/"),
+                self.pattern
+            ));
+        }
+        if self.covers(lower, &alphabet) {
+            return Some(format!(
+                "Route '{}' is unreachable because '{}' matches every path it accepts",
+                lower.pattern, self.pattern
+            ));
+        }
+        None
+    }
+
+    // 同時在兩個自動機中前進，記錄是否出現過 lower 更具體的路徑段，返回找到的路徑
+    fn find_precedence_overlap(
+        &self,
+        lower: &RoutePattern,
+        alphabet: &[Option<&str>],
+    ) -> Option<Vec<String>> {
+        let (higher_end, lower_end) = (self.segments.len(), lower.segments.len());
+        let mut visited = HashSet::new();
+        let mut queue = VecDeque::new();
+        for higher_state in self.closure([0]) {
+            for lower_state in lower.closure([0]) {
+                visited.insert((higher_state, lower_state, false));
+                queue.push_back((higher_state, lower_state, false, Vec::new()));
+            }
+        }
+
+        while let Some((higher_state, lower_state, shadowed, path)) = queue.pop_front() {
+            if shadowed && higher_state == higher_end && lower_state == lower_end {
+                return Some(path);
+            }
+            let (Some(higher_segment), Some(lower_segment)) = (
+                self.segments.get(higher_state),
+                lower.segments.get(lower_state),
+            ) else {
+                continue;
+            };
+            let shadowed = shadowed || lower_segment.rank() > higher_segment.rank();
+            for &token in alphabet {
+                let higher_next = self.closure(self.step(higher_state, token));
+                let lower_next = lower.closure(lower.step(lower_state, token));
+                if higher_next.is_empty() || lower_next.is_empty() {
+                    continue;
+                }
+                let mut path = path.clone();
+                path.push(match token {
+                    Some(literal) => literal.to_string(),
+                    None => format!("[{}]", lower_segment.name().unwrap_or_default()),
+                });
+                for &higher_next in &higher_next {
+                    for &lower_next in &lower_next {
+                        if visited.insert((higher_next, lower_next, shadowed)) {
+                            queue.push_back((higher_next, lower_next, shadowed, path.clone()));
+                        }
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    // lower 接受的每條路徑 self 都接受；self 的狀態按子集跟蹤
+    fn covers(&self, lower: &RoutePattern, alphabet: &[Option<&str>]) -> bool {
+        let lower_end = lower.segments.len();
+        let higher_end = self.segments.len();
+        let mut visited = HashSet::new();
+        let mut queue = VecDeque::new();
+        let higher_start = self.closure([0]);
+        for lower_state in lower.closure([0]) {
+            visited.insert((lower_state, higher_start.clone()));
+            queue.push_back((lower_state, higher_start.clone()));
+        }
+
+        while let Some((lower_state, higher_states)) = queue.pop_front() {
+            if lower_state == lower_end && !higher_states.contains(&higher_end) {
+                return false;
+            }
+            for &token in alphabet {
+                let lower_next = lower.closure(lower.step(lower_state, token));
+                if lower_next.is_empty() {
+                    continue;
+                }
+                let higher_next = self.closure(
+                    higher_states
+                        .iter()
+                        .flat_map(|&state| self.step(state, token)),
+                );
+                for lower_next in lower_next {
+                    if visited.insert((lower_next, higher_next.clone())) {
+                        queue.push_back((lower_next, higher_next.clone()));
+                    }
+                }
+            }
+        }
+        true
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let mut names = Vec::new();
+        for name in self.segments.iter().filter_map(Segment::name) {
+            if names.contains(&name) {
+                return Err(format!(
+                    "Route '{}' declares parameter '{}' more than once",
+                    self.pattern, name
+                ));
+            }
+            names.push(name);
+        }
+        Ok(())
+    }
+
+    pub fn matches(&self, path: &str) -> Option<HashMap<String, String>> {
+        let path_segments: Vec<&str> = split_path(strip_query(path)).collect();
+        let mut captured = HashMap::new();
+        if match_segments(&self.segments, &path_segments, &mut captured) {
+            Some(captured)
+        } else {
+            None
+        }
+    }
+}
+
+fn strip_query(path: &str) -> &str {
+    path.split(['?', '#']).next().unwrap_or_default()
+}
+
+fn split_path(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|segment| !segment.is_empty())
+}
+
+fn match_segments(
+    segments: &[Segment],
+    path: &[&str],
+    captured: &mut HashMap<String, String>,
+) -> bool {
+    let Some((segment, rest)) = segments.split_first() else {
+        // 模式已用盡時必須恰好消耗完整個路徑，前綴匹配需顯式使用 [...slug]
+        return path.is_empty();
+    };
+
+    match segment {
+        Segment::Static(literal) => match path.split_first() {
+            Some((head, tail)) if head == literal => match_segments(rest, tail, captured),
+            _ => false,
+        },
+        Segment::Dynamic(name) => {
+            let Some((head, tail)) = path.split_first() else {
+                return false;
+            };
+            captured.insert(name.clone(), head.to_string());
+            if match_segments(rest, tail, captured) {
+                return true;
+            }
+            captured.remove(name);
+            false
+        }
+        Segment::OptionalDynamic(name) => {
+            if let Some((head, tail)) = path.split_first() {
+                captured.insert(name.clone(), head.to_string());
+                if match_segments(rest, tail, captured) {
+                    return true;
+                }
+                captured.remove(name);
+            }
+            match_segments(rest, path, captured)
+        }
+        Segment::CatchAll(name) | Segment::OptionalCatchAll(name) => {
+            let min = usize::from(matches!(segment, Segment::CatchAll(_)));
+            // 優先盡可能多地捕獲路徑段
+            for taken in (min..=path.len()).rev() {
+                if taken > 0 {
+                    captured.insert(name.clone(), path[..taken].join("/"));
+                }
+                if match_segments(rest, &path[taken..], captured) {
+                    return true;
+                }
+                captured.remove(name);
+            }
+            false
+        }
+    }
 }
 
 pub struct CombinedParamsProcessor {
-    processors: Vec<(String, Box<dyn ParamsProcessor>)>,
+    processors: Vec<(RoutePattern, Box<dyn ParamsProcessor>)>,
 }
 
 impl Default for CombinedParamsProcessor {
@@ -26,9 +349,14 @@ impl CombinedParamsProcessor {
         }
     }
 
-    pub fn add<P: ParamsProcessor + 'static>(mut self, path_prefix: &str, processor: P) -> Self {
-        self.processors
-            .push((path_prefix.to_string(), Box::new(processor)));
+    pub fn add<P: ParamsProcessor + 'static>(mut self, pattern: &str, processor: P) -> Self {
+        let route = RoutePattern::new(pattern);
+        // 保持按特異性降序排列，相同特異性時按註冊順序
+        let specificity = route.specificity();
+        let index = self
+            .processors
+            .partition_point(|(existing, _)| existing.specificity() >= specificity);
+        self.processors.insert(index, (route, Box::new(processor)));
         self
     }
 }
@@ -39,9 +367,11 @@ impl ParamsProcessor for CombinedParamsProcessor {
         path: &str,
         params: &HashMap<String, String>,
     ) -> serde_json::Map<String, Value> {
-        for (prefix, processor) in &self.processors {
-            if path.starts_with(prefix) {
-                return processor.process(path, params);
+        for (route, processor) in &self.processors {
+            if let Some(captured) = route.matches(path) {
+                let mut merged = params.clone();
+                merged.extend(captured);
+                return processor.process(path, &merged);
             }
         }
         params
@@ -49,4 +379,26 @@ impl ParamsProcessor for CombinedParamsProcessor {
             .map(|(k, v)| (k.clone(), Value::String(v.clone())))
             .collect()
     }
+
+    fn validate(&self) -> Result<(), String> {
+        let mut errors = Vec::new();
+        for (i, (route, processor)) in self.processors.iter().enumerate() {
+            if let Err(e) = route.validate() {
+                errors.push(e);
+            }
+            if let Err(e) = processor.validate() {
+                errors.push(e);
+            }
+            for (higher, _) in &self.processors[..i] {
+                if let Some(conflict) = higher.conflict_with(route) {
+                    errors.push(conflict);
+                }
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join("; "))
+        }
+    }
 }
@@ -1,6 +1,9 @@
+use crate::config::get_global_config;
 use crate::init::RENDERER;
 use crate::params::ParamsProcessor;
-use crate::state::get_global_state;
+use crate::state::{
+    get_global_state, global_cookie_manager, CookieManager, RequestSession, Session, SessionManager,
+};
 use crate::template::Template;
 use serde_json::{json, Value};
 use std::collections::HashMap;
@@ -39,18 +42,94 @@ impl SsrRenderer {
         render_fn: F,
         #[cfg(feature = "island")] processor: &dyn IslandProcessor,
     ) -> Result<(String, Vec<String>), String>
+    where
+        F: FnOnce(&str) -> Result<String, String>,
+    {
+        // 使用全局狀態的時鐘和簽名密鑰
+        let mut cookies = global_cookie_manager()
+            .unwrap_or_else(|| CookieManager::from_config(get_global_config()));
+        self.render_with_cookies(
+            path,
+            params,
+            &mut cookies,
+            render_fn,
+            #[cfg(feature = "island")]
+            processor,
+        )
+    }
+
+    // 使用本次請求的 Cookie 容器渲染，返回的 Set-Cookie 只包含該請求中變更的 Cookie
+    // 瀏覽器可能忽略的 Cookie（如 SameSite=None 但未設置 Secure）不影響渲染，由調用者通過 cookies.warnings() 讀取
+    pub fn render_with_cookies<F>(
+        &self,
+        path: &str,
+        params: HashMap<String, String>,
+        cookies: &mut CookieManager,
+        render_fn: F,
+        #[cfg(feature = "island")] processor: &dyn IslandProcessor,
+    ) -> Result<(String, Vec<String>), String>
+    where
+        F: FnOnce(&str) -> Result<String, String>,
+    {
+        let cookie_name = get_global_config().get_session_cookie_name();
+        let mut session = if cookies.get(&cookie_name).is_some() {
+            with_session_manager(|manager| manager.load_request_session(cookies))?
+        } else {
+            RequestSession::new()
+        };
+        self.render_with_session(
+            path,
+            params,
+            cookies,
+            &mut session,
+            render_fn,
+            #[cfg(feature = "island")]
+            processor,
+        )
+    }
+
+    // 與 render_with_cookies 相同，但使用調用者已載入（並可能已修改）的 Session
+    pub fn render_with_session<F>(
+        &self,
+        path: &str,
+        params: HashMap<String, String>,
+        cookies: &mut CookieManager,
+        session: &mut RequestSession,
+        render_fn: F,
+        #[cfg(feature = "island")] processor: &dyn IslandProcessor,
+    ) -> Result<(String, Vec<String>), String>
     where
         F: FnOnce(&str) -> Result<String, String>,
     {
         let processed_params = self.params_processor.process(path, &params);
 
+        // 取出一次性提示，渲染成功後隨 Session 一併保存，使其只顯示一次
+        let flash = if session.get().is_some_and(|s| s.has_flashes()) {
+            session.get_mut().take_flashes()
+        } else {
+            Vec::new()
+        };
+
+        // 開啟 CSRF 保護時確保 Session 中有令牌，供表單嵌入
+        let csrf_token = if get_global_config().get_csrf_protection() {
+            Some(with_session_manager(|manager| manager.csrf_token(session))?)
+        } else {
+            session.get().and_then(Session::csrf_token)
+        };
+
         let props = json!({
             "url": path,
             "params": processed_params,
+            "flash": flash,
+            "csrf_token": csrf_token,
         });
 
         let content = render_fn(&props.to_string())?;
 
+        if session.is_modified() {
+            with_session_manager(|manager| manager.commit_request_session(session, cookies))??;
+        }
+
         #[cfg(feature = "island")]
         {
             let mut rendered = serde_json::from_str::<Value>(&content)
@@ -68,12 +147,8 @@ impl SsrRenderer {
             };
             let islands_value = self.island_manager.process_islands(processor, &context);
 
-            let global_state = get_global_state().read().map_err(|e| e.to_string())?;
-            let cookie_manager = global_state
-                .get_cookie_manager()
-                .lock()
-                .map_err(|e| e.to_string())?;
-            let cookies = cookie_manager.to_header_strings();
+            cookies.validate()?;
+            let cookies = cookies.to_header_strings();
 
             let html = self.template.render(&rendered, Some(&islands_value))?;
 
@@ -83,12 +158,8 @@ impl SsrRenderer {
         {
             let rendered = serde_json::from_str::<Value>(&content)
                 .map_err(|e| format!("Failed to parse render result: {}", e))?;
-            let global_state = get_global_state().read().map_err(|e| e.to_string())?;
-            let cookie_manager = global_state
-                .get_cookie_manager()
-                .lock()
-                .map_err(|e| e.to_string())?;
-            let cookies = cookie_manager.to_header_strings();
+            cookies.validate()?;
+            let cookies = cookies.to_header_strings();
 
             let html = self.template.render(&rendered)?;
 
@@ -121,6 +192,15 @@ impl SsrRenderer {
     }
 }
 
+fn with_session_manager<R>(f: impl FnOnce(&mut SessionManager) -> R) -> Result<R, String> {
+    let global_state = get_global_state().read().map_err(|e| e.to_string())?;
+    let mut session_manager = global_state
+        .get_session_manager()
+        .write()
+        .map_err(|e| e.to_string())?;
+    Ok(f(&mut session_manager))
+}
+
 pub fn get_renderer() -> &'static SsrRenderer {
     RENDERER.get().expect("Renderer not initialized")
 }
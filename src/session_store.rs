@@ -0,0 +1,180 @@
+use crate::state::Session;
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+// 文件名中只保留字母數字和 `-`、`_`
+const FILE_NAME_ENCODE_SET: &AsciiSet = &NON_ALPHANUMERIC.remove(b'-').remove(b'_');
+
+pub trait SessionStore: Send + Sync {
+    fn load(&self, session_id: &str) -> Result<Option<Session>, String>;
+    fn save(&self, session_id: &str, session: &Session) -> Result<(), String>;
+    fn remove(&self, session_id: &str) -> Result<(), String>;
+    fn retain(&self, keep: &mut dyn FnMut(&str, &Session) -> bool) -> Result<(), String>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SessionStoreKind {
+    Memory,
+    File(PathBuf),
+}
+
+impl SessionStoreKind {
+    pub fn create(&self) -> Result<Box<dyn SessionStore>, String> {
+        match self {
+            SessionStoreKind::Memory => Ok(Box::new(MemorySessionStore::new())),
+            SessionStoreKind::File(dir) => Ok(Box::new(FileSessionStore::new(dir.clone())?)),
+        }
+    }
+}
+
+pub struct MemorySessionStore {
+    sessions: Mutex<HashMap<String, Session>>,
+}
+
+impl Default for MemorySessionStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemorySessionStore {
+    pub fn new() -> Self {
+        Self {
+            sessions: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl SessionStore for MemorySessionStore {
+    fn load(&self, session_id: &str) -> Result<Option<Session>, String> {
+        let sessions = self.sessions.lock().map_err(|e| e.to_string())?;
+        Ok(sessions.get(session_id).cloned())
+    }
+
+    fn save(&self, session_id: &str, session: &Session) -> Result<(), String> {
+        let mut sessions = self.sessions.lock().map_err(|e| e.to_string())?;
+        sessions.insert(session_id.to_string(), session.clone());
+        Ok(())
+    }
+
+    fn remove(&self, session_id: &str) -> Result<(), String> {
+        let mut sessions = self.sessions.lock().map_err(|e| e.to_string())?;
+        sessions.remove(session_id);
+        Ok(())
+    }
+
+    fn retain(&self, keep: &mut dyn FnMut(&str, &Session) -> bool) -> Result<(), String> {
+        let mut sessions = self.sessions.lock().map_err(|e| e.to_string())?;
+        sessions.retain(|session_id, session| keep(session_id, session));
+        Ok(())
+    }
+}
+
+// 每個 Session 保存為目錄下的一個 JSON 文件，重啟或多實例共享目錄時不會丟失登錄狀態
+pub struct FileSessionStore {
+    dir: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl FileSessionStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Result<Self, String> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)
+            .map_err(|e| format!("Failed to create session directory {:?}: {}", dir, e))?;
+        Ok(Self {
+            dir,
+            lock: Mutex::new(()),
+        })
+    }
+
+    // nanoid 的字母表可以配置，ID 中字母數字和 `-`、`_` 以外的字符都編碼後作為文件名，
+    // 同時防止通過 Session ID 訪問目錄以外的文件
+    fn session_path(&self, session_id: &str) -> Result<PathBuf, String> {
+        if session_id.is_empty() {
+            return Err("Invalid session id: empty".to_string());
+        }
+        let file_name = utf8_percent_encode(session_id, FILE_NAME_ENCODE_SET);
+        Ok(self.dir.join(format!("{}.json", file_name)))
+    }
+
+    fn read(&self, path: &PathBuf) -> Result<Option<Session>, String> {
+        match fs::read_to_string(path) {
+            Ok(content) => serde_json::from_str::<Session>(&content)
+                .map(Some)
+                .map_err(|e| format!("Failed to parse session file {:?}: {}", path, e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(format!("Failed to read session file {:?}: {}", path, e)),
+        }
+    }
+}
+
+impl SessionStore for FileSessionStore {
+    fn load(&self, session_id: &str) -> Result<Option<Session>, String> {
+        let path = self.session_path(session_id)?;
+        let _guard = self.lock.lock().map_err(|e| e.to_string())?;
+        self.read(&path)
+    }
+
+    fn save(&self, session_id: &str, session: &Session) -> Result<(), String> {
+        let path = self.session_path(session_id)?;
+        let content = serde_json::to_string(session).map_err(|e| e.to_string())?;
+        let _guard = self.lock.lock().map_err(|e| e.to_string())?;
+        // 先寫臨時文件再重命名，避免讀到寫了一半的內容
+        let tmp_path = path.with_extension("json.tmp");
+        fs::write(&tmp_path, content)
+            .and_then(|_| fs::rename(&tmp_path, &path))
+            .map_err(|e| format!("Failed to write session file {:?}: {}", path, e))
+    }
+
+    fn remove(&self, session_id: &str) -> Result<(), String> {
+        let path = self.session_path(session_id)?;
+        let _guard = self.lock.lock().map_err(|e| e.to_string())?;
+        match fs::remove_file(&path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                Err(format!("Failed to remove session file {:?}: {}", path, e))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn retain(&self, keep: &mut dyn FnMut(&str, &Session) -> bool) -> Result<(), String> {
+        let _guard = self.lock.lock().map_err(|e| e.to_string())?;
+        let entries = fs::read_dir(&self.dir)
+            .map_err(|e| format!("Failed to read session directory {:?}: {}", self.dir, e))?;
+        // 單個文件出錯時繼續處理其它文件，刪除失敗的錯誤在最後返回
+        let mut result = Ok(());
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                continue;
+            }
+            let Some(session_id) = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(|stem| percent_decode_str(stem).decode_utf8().ok())
+            else {
+                continue;
+            };
+            let Ok(content) = fs::read_to_string(&path) else {
+                continue;
+            };
+            let Ok(session) = serde_json::from_str::<Session>(&content) else {
+                // 損壞的文件無法再被載入，直接刪除
+                let _ = fs::remove_file(&path);
+                continue;
+            };
+            if !keep(&session_id, &session) {
+                if let Err(e) = fs::remove_file(&path) {
+                    result = result.and(Err(format!(
+                        "Failed to remove session file {:?}: {}",
+                        path, e
+                    )));
+                }
+            }
+        }
+        result
+    }
+}
@@ -1,21 +1,82 @@
+use crate::clock::{Clock, SystemClock};
 use crate::config::SsrkitConfig;
+use crate::session_store::SessionStore;
 use crate::Cache;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
 use chrono::{DateTime, Duration, Utc};
+use hmac::{Hmac, Mac};
 use nanoid::nanoid;
-use std::collections::HashMap;
-use std::sync::{Arc, Mutex, RwLock};
-use std::time::Instant;
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, CONTROLS};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::ops::{Deref, DerefMut};
+use std::path::Path;
+use std::sync::{mpsc, Arc, RwLock};
+use std::thread::{self, JoinHandle};
 
+type HmacSha256 = Hmac<Sha256>;
+
+// RFC 6265 cookie-octet 以外的字符，以及 `%` 本身，輸出時需要百分號編碼
+const COOKIE_VALUE_ENCODE_SET: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b',')
+    .add(b';')
+    .add(b'\\')
+    .add(b'%');
+
+// 瀏覽器對單個 Cookie 名稱與值的長度限制
+const MAX_COOKIE_SIZE: usize = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SameSite {
+    Strict,
+    Lax,
+    None,
+}
+
+impl std::fmt::Display for SameSite {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SameSite::Strict => write!(f, "Strict"),
+            SameSite::Lax => write!(f, "Lax"),
+            SameSite::None => write!(f, "None"),
+        }
+    }
+}
+
+impl std::str::FromStr for SameSite {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "strict" => Ok(SameSite::Strict),
+            "lax" => Ok(SameSite::Lax),
+            "none" => Ok(SameSite::None),
+            _ => Err(format!("Invalid SameSite value: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Cookie {
     pub name: String,
     pub value: String,
     pub expires: Option<DateTime<Utc>>,
+    #[serde(default, with = "max_age_seconds")]
     pub max_age: Option<Duration>,
     pub domain: Option<String>,
     pub path: Option<String>,
     pub secure: bool,
     pub http_only: bool,
-    pub same_site: Option<String>,
+    pub same_site: Option<SameSite>,
+    pub partitioned: bool,
 }
 
 impl Cookie {
@@ -30,11 +91,166 @@ impl Cookie {
             secure: false,
             http_only: false,
             same_site: None,
+            partitioned: false,
+        }
+    }
+
+    // 解析請求的 `Cookie:` 頭，保留原始順序（包括重名的 Cookie）
+    pub fn parse_cookie_header(header: &str) -> Vec<Cookie> {
+        header
+            .split(';')
+            .filter_map(|pair| {
+                let (name, value) = pair.split_once('=')?;
+                let name = name.trim();
+                if name.is_empty() {
+                    return None;
+                }
+                Some(Cookie::new(name.to_string(), decode_value(value.trim())))
+            })
+            .collect()
+    }
+
+    // 解析 `Set-Cookie` 頭，主要用於測試中的往返驗證
+    pub fn parse_set_cookie(header: &str) -> Result<Cookie, String> {
+        let mut parts = header.split(';');
+        let (name, value) = parts
+            .next()
+            .and_then(|pair| pair.split_once('='))
+            .ok_or_else(|| format!("Invalid Set-Cookie header: {}", header))?;
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(format!(
+                "Missing cookie name in Set-Cookie header: {}",
+                header
+            ));
+        }
+
+        let value = value.trim();
+        let raw = value
+            .strip_prefix('"')
+            .and_then(|v| v.strip_suffix('"'))
+            .unwrap_or(value);
+        if !is_token(name) || !raw.bytes().all(is_cookie_octet) {
+            return Err(format!("Invalid cookie name or value: {}", header));
+        }
+
+        let mut cookie = Cookie::new(name.to_string(), decode_value(value));
+        for attribute in parts {
+            let (key, value) = match attribute.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => (attribute.trim(), ""),
+            };
+            match key.to_ascii_lowercase().as_str() {
+                "expires" => {
+                    let expires = DateTime::parse_from_rfc2822(value)
+                        .map_err(|e| format!("Invalid Expires attribute '{}': {}", value, e))?;
+                    cookie.expires = Some(expires.with_timezone(&Utc));
+                }
+                "max-age" => {
+                    let seconds = value
+                        .parse::<i64>()
+                        .map_err(|e| format!("Invalid Max-Age attribute '{}': {}", value, e))?;
+                    cookie.max_age = Some(Duration::seconds(seconds));
+                }
+                "domain" => {
+                    cookie.domain = Some(value.trim_start_matches('.').to_ascii_lowercase())
+                }
+                "path" => cookie.path = Some(value.to_string()),
+                "secure" => cookie.secure = true,
+                "httponly" => cookie.http_only = true,
+                "samesite" => cookie.same_site = Some(value.parse()?),
+                "partitioned" => cookie.partitioned = true,
+                _ => {}
+            }
+        }
+        Ok(cookie)
+    }
+
+    // 檢查名稱、前綴規則、Partitioned 屬性和大小限制
+    pub fn validate(&self) -> Result<(), String> {
+        if !is_token(&self.name) {
+            return Err(format!("Invalid cookie name: '{}'", self.name));
+        }
+
+        let name = self.name.to_ascii_lowercase();
+        if name.starts_with("__secure-") && !self.secure {
+            return Err(format!(
+                "Cookie '{}' uses the __Secure- prefix and must be Secure",
+                self.name
+            ));
+        }
+        if name.starts_with("__host-")
+            && (!self.secure || self.domain.is_some() || self.path.as_deref() != Some("/"))
+        {
+            return Err(format!(
+                "Cookie '{}' uses the __Host- prefix and must be Secure, have Path=/ and no Domain",
+                self.name
+            ));
+        }
+        if self.partitioned && !self.secure {
+            return Err(format!("Partitioned cookie '{}' must be Secure", self.name));
+        }
+        // 控制字符和分號會截斷或注入 Set-Cookie 屬性
+        for (attribute, value) in [("Path", &self.path), ("Domain", &self.domain)] {
+            if value
+                .as_deref()
+                .is_some_and(|v| v.bytes().any(|b| b.is_ascii_control() || b == b';'))
+            {
+                return Err(format!(
+                    "Cookie '{}' has an invalid {} attribute",
+                    self.name, attribute
+                ));
+            }
+        }
+
+        let size = self.name.len() + self.encoded_value().len();
+        if size > MAX_COOKIE_SIZE {
+            return Err(format!(
+                "Cookie '{}' is {} bytes, exceeding the {} byte limit",
+                self.name, size, MAX_COOKIE_SIZE
+            ));
+        }
+        Ok(())
+    }
+
+    // 不會導致錯誤但瀏覽器可能拒絕或忽略的設置
+    pub fn warnings(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        if self.same_site == Some(SameSite::None) && !self.secure {
+            warnings.push(format!(
+                "Cookie '{}' has SameSite=None without Secure and will be rejected by browsers",
+                self.name
+            ));
+        }
+        warnings
+    }
+
+    // 刪除用的 Cookie：空值、Max-Age=0 和過去的 Expires，保留 Path/Domain 以匹配原 Cookie
+    // 請求帶來的帶前綴 Cookie 沒有屬性，補上前綴要求的屬性，否則瀏覽器會拒絕刪除頭
+    pub fn to_removal(&self) -> Cookie {
+        let mut removal = Cookie {
+            value: String::new(),
+            expires: DateTime::from_timestamp(0, 0),
+            max_age: Some(Duration::zero()),
+            ..self.clone()
+        };
+        let name = self.name.to_ascii_lowercase();
+        if name.starts_with("__secure-") || name.starts_with("__host-") {
+            removal.secure = true;
+        }
+        if name.starts_with("__host-") {
+            removal.domain = None;
+            removal.path = Some("/".to_string());
         }
+        removal
+    }
+
+    fn encoded_value(&self) -> String {
+        utf8_percent_encode(&self.value, COOKIE_VALUE_ENCODE_SET).to_string()
     }
 
     pub fn to_header_string(&self) -> String {
-        let mut parts = vec![format!("{}={}", self.name, self.value)];
+        let mut parts = vec![format!("{}={}", self.name, self.encoded_value())];
 
         if let Some(expires) = self.expires {
             parts.push(format!(
@@ -57,16 +273,143 @@ impl Cookie {
         if self.http_only {
             parts.push("HttpOnly".to_string());
         }
-        if let Some(ref same_site) = self.same_site {
+        if let Some(same_site) = self.same_site {
             parts.push(format!("SameSite={}", same_site));
         }
+        if self.partitioned {
+            parts.push("Partitioned".to_string());
+        }
 
         parts.join("; ")
     }
 }
 
+// RFC 7230 token，用於 Cookie 名稱
+fn is_token(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b))
+}
+
+// RFC 6265 cookie-octet
+fn is_cookie_octet(b: u8) -> bool {
+    matches!(b, 0x21 | 0x23..=0x2B | 0x2D..=0x3A | 0x3C..=0x5B | 0x5D..=0x7E)
+}
+
+fn signing_mac(key: &[u8], name: &str, value: &str) -> HmacSha256 {
+    let mut mac =
+        <HmacSha256 as Mac>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    // 簽名同時覆蓋名稱，防止把一個 Cookie 的值挪到另一個 Cookie 上
+    mac.update(name.as_bytes());
+    mac.update(b"=");
+    mac.update(value.as_bytes());
+    mac
+}
+
+fn sign_value(key: &[u8], name: &str, value: &str) -> String {
+    let signature = signing_mac(key, name, value).finalize().into_bytes();
+    format!("{}.{}", value, URL_SAFE_NO_PAD.encode(signature))
+}
+
+// 比較耗時只取決於長度，避免通過響應時間逐字節猜出令牌
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+// chrono::Duration 沒有實現 serde，以秒數保存
+mod max_age_seconds {
+    use chrono::Duration;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        max_age: &Option<Duration>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        max_age.map(|d| d.num_seconds()).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Duration>, D::Error> {
+        Ok(Option::<i64>::deserialize(deserializer)?.map(Duration::seconds))
+    }
+}
+
+fn verify_value(keys: &[Vec<u8>], name: &str, signed: &str) -> Option<String> {
+    let (value, signature) = signed.rsplit_once('.')?;
+    let signature = URL_SAFE_NO_PAD.decode(signature).ok()?;
+    keys.iter()
+        .any(|key| {
+            signing_mac(key, name, value)
+                .verify_slice(&signature)
+                .is_ok()
+        })
+        .then(|| value.to_string())
+}
+
+fn decode_value(raw: &str) -> String {
+    let unquoted = raw
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .unwrap_or(raw);
+    percent_decode_str(unquoted)
+        .decode_utf8()
+        .map(|v| v.into_owned())
+        .unwrap_or_else(|_| unquoted.to_string())
+}
+
+// Cookie 的身份與瀏覽器一致：名稱 + Domain + Path
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct CookieKey {
+    name: String,
+    domain: Option<String>,
+    path: Option<String>,
+}
+
+impl CookieKey {
+    fn new(name: &str, domain: Option<&str>, path: Option<&str>) -> Self {
+        Self {
+            name: name.to_string(),
+            domain: domain.map(|d| d.trim_start_matches('.').to_ascii_lowercase()),
+            path: path.map(str::to_string),
+        }
+    }
+
+    fn of(cookie: &Cookie) -> Self {
+        Self::new(
+            &cookie.name,
+            cookie.domain.as_deref(),
+            cookie.path.as_deref(),
+        )
+    }
+}
+
+// RFC 6265 5.1.3
+fn domain_matches(cookie_domain: &str, host: &str) -> bool {
+    let cookie_domain = cookie_domain.trim_start_matches('.');
+    host.eq_ignore_ascii_case(cookie_domain)
+        || (host.len() > cookie_domain.len()
+            && host.is_char_boundary(host.len() - cookie_domain.len() - 1)
+            && host[host.len() - cookie_domain.len() - 1..]
+                .eq_ignore_ascii_case(&format!(".{}", cookie_domain)))
+}
+
+// RFC 6265 5.1.4
+fn path_matches(cookie_path: &str, request_path: &str) -> bool {
+    request_path == cookie_path
+        || (request_path.starts_with(cookie_path)
+            && (cookie_path.ends_with('/') || request_path[cookie_path.len()..].starts_with('/')))
+}
+
+// 每個請求獨立的 Cookie 容器：
+// 請求帶來的 Cookie 通過 add_original 載入，只有在本次請求中變更過的 Cookie 才會輸出
 pub struct CookieManager {
-    cookies: HashMap<String, Cookie>,
+    cookies: HashMap<CookieKey, Cookie>,
+    changed: HashSet<CookieKey>,
+    removed: HashMap<CookieKey, Cookie>,
+    signing_keys: Vec<Vec<u8>>,
+    clock: Arc<dyn Clock>,
 }
 
 impl Default for CookieManager {
@@ -79,112 +422,1035 @@ impl CookieManager {
     pub fn new() -> Self {
         Self {
             cookies: HashMap::new(),
+            changed: HashSet::new(),
+            removed: HashMap::new(),
+            signing_keys: Vec::new(),
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    pub fn with_signing_keys(mut self, keys: Vec<Vec<u8>>) -> Self {
+        self.signing_keys = keys;
+        self
+    }
+
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn from_config(config: &SsrkitConfig) -> Self {
+        Self::new().with_signing_keys(config.get_cookie_signing_keys())
+    }
+
+    // 從請求的 `Cookie:` 頭構建，重名時保留第一個（瀏覽器會把路徑最具體的放在前面）
+    pub fn from_cookie_header(header: &str) -> Self {
+        Self::new().with_cookie_header(header)
+    }
+
+    pub fn with_cookie_header(mut self, header: &str) -> Self {
+        for cookie in Cookie::parse_cookie_header(header) {
+            if self.get(&cookie.name).is_none() {
+                self.add_original(cookie);
+            }
         }
+        self
+    }
+
+    pub fn add_original(&mut self, cookie: Cookie) {
+        let key = CookieKey::of(&cookie);
+        self.changed.remove(&key);
+        self.cookies.insert(key, cookie);
     }
 
     pub fn add(&mut self, cookie: Cookie) {
-        self.cookies.insert(cookie.name.clone(), cookie);
+        let key = CookieKey::of(&cookie);
+        // 未設置 Path 的 Cookie 與默認 Path=/ 的刪除頭作用於同一個 Cookie
+        let path = cookie.path.as_deref().unwrap_or("/");
+        self.removed.retain(|k, _| {
+            k.name != key.name || k.domain != key.domain || k.path.as_deref() != Some(path)
+        });
+        self.changed.insert(key.clone());
+        self.cookies.insert(key, cookie);
     }
 
+    // 校驗失敗時返回錯誤，成功時返回不影響設置的警告
+    pub fn try_add(&mut self, cookie: Cookie) -> Result<Vec<String>, String> {
+        cookie.validate()?;
+        let warnings = cookie.warnings();
+        self.add(cookie);
+        Ok(warnings)
+    }
+
+    // 按名稱查找，存在多個同名 Cookie 時返回 Path 最長的
     pub fn get(&self, name: &str) -> Option<&Cookie> {
-        self.cookies.get(name)
+        self.get_all(name)
+            .into_iter()
+            .max_by_key(|c| c.path.as_deref().map_or(0, str::len))
     }
 
+    pub fn get_all(&self, name: &str) -> Vec<&Cookie> {
+        self.cookies.values().filter(|c| c.name == name).collect()
+    }
+
+    pub fn get_scoped(
+        &self,
+        name: &str,
+        domain: Option<&str>,
+        path: Option<&str>,
+    ) -> Option<&Cookie> {
+        self.cookies.get(&CookieKey::new(name, domain, path))
+    }
+
+    // 按瀏覽器的規則選出會發送給 host + request_path 的 Cookie，未設置 Domain/Path 的視為匹配
+    pub fn get_matching(&self, name: &str, host: &str, request_path: &str) -> Option<&Cookie> {
+        self.get_all(name)
+            .into_iter()
+            .filter(|c| c.domain.as_deref().is_none_or(|d| domain_matches(d, host)))
+            .filter(|c| {
+                c.path
+                    .as_deref()
+                    .is_none_or(|p| path_matches(p, request_path))
+            })
+            .max_by_key(|c| c.path.as_deref().map_or(0, str::len))
+    }
+
+    // 使用第一個密鑰簽名，值的格式為 `value.signature`
+    pub fn add_signed(&mut self, mut cookie: Cookie) -> Result<(), String> {
+        let key = self
+            .signing_keys
+            .first()
+            .ok_or("No cookie signing key configured")?;
+        cookie.value = sign_value(key, &cookie.name, &cookie.value);
+        self.add(cookie);
+        Ok(())
+    }
+
+    // 任一密鑰驗證通過即視為有效，返回去掉簽名後的 Cookie
+    pub fn get_signed(&self, name: &str) -> Option<Cookie> {
+        let cookie = self.get(name)?;
+        let value = verify_value(&self.signing_keys, name, &cookie.value)?;
+        Some(Cookie {
+            value,
+            ..cookie.clone()
+        })
+    }
+
+    // 刪除所有同名 Cookie 並通知瀏覽器，使用 Cookie 已知的 Path/Domain，未知時使用 Path=/
     pub fn delete(&mut self, name: &str) {
-        self.cookies.remove(name);
+        let cookies: Vec<Cookie> = self.get_all(name).into_iter().cloned().collect();
+        for cookie in cookies {
+            self.remove(cookie);
+        }
+    }
+
+    pub fn delete_scoped(&mut self, name: &str, domain: Option<&str>, path: Option<&str>) {
+        if let Some(cookie) = self.get_scoped(name, domain, path).cloned() {
+            self.remove(cookie);
+        }
+    }
+
+    // 請求帶來的 Cookie 沒有 Path/Domain，刪除頭默認使用 Path=/（本庫設置的 Session 等 Cookie
+    // 都使用該路徑），其它 Path/Domain 需要由調用者提供與設置時相同的屬性
+    pub fn remove(&mut self, cookie: Cookie) {
+        let key = CookieKey::of(&cookie);
+        self.cookies.remove(&key);
+        self.changed.remove(&key);
+        // 請求帶來的 Cookie 與帶屬性的刪除請求不是同一個鍵，也需要從容器中移除
+        if cookie.domain.is_some() || cookie.path.is_some() {
+            let original = CookieKey::new(&cookie.name, None, None);
+            if !self.changed.contains(&original) {
+                self.cookies.remove(&original);
+            }
+        }
+        let mut removal = cookie.to_removal();
+        removal.path.get_or_insert_with(|| "/".to_string());
+        // 按實際輸出的屬性記錄，之後 add 相同 Path/Domain 的 Cookie 時才能撤銷該刪除
+        self.removed.insert(CookieKey::of(&removal), removal);
+    }
+
+    pub fn removed(&self) -> impl Iterator<Item = &Cookie> {
+        self.removed.values()
     }
 
     pub fn update(&mut self, name: &str, value: String) {
-        if let Some(cookie) = self.cookies.get_mut(name) {
-            cookie.value = value;
+        for (key, cookie) in self.cookies.iter_mut().filter(|(k, _)| k.name == name) {
+            cookie.value = value.clone();
+            self.changed.insert(key.clone());
         }
     }
 
     pub fn refresh(&mut self, name: &str) {
-        if let Some(cookie) = self.cookies.get_mut(name) {
-            cookie.expires = Some(Utc::now() + chrono::Duration::days(30));
+        let expires = self.clock.now() + chrono::Duration::days(30);
+        for (key, cookie) in self.cookies.iter_mut().filter(|(k, _)| k.name == name) {
+            cookie.expires = Some(expires);
+            self.changed.insert(key.clone());
         }
     }
 
+    // 本次請求中設置或更新過的 Cookie
+    fn outgoing(&self) -> impl Iterator<Item = &Cookie> {
+        self.changed.iter().filter_map(|key| self.cookies.get(key))
+    }
+
+    // 校驗所有將要輸出的 Cookie
+    pub fn validate(&self) -> Result<(), String> {
+        self.outgoing()
+            .chain(self.removed.values())
+            .try_for_each(Cookie::validate)
+    }
+
+    pub fn warnings(&self) -> Vec<String> {
+        self.outgoing().flat_map(Cookie::warnings).collect()
+    }
+
     pub fn to_header_strings(&self) -> Vec<String> {
-        self.cookies
-            .values()
+        self.outgoing()
+            .chain(self.removed.values())
             .map(|c| c.to_header_string())
             .collect()
     }
 }
 
+// 加密 Cookie 容器：內部保存明文，輸出時使用 AES-256-GCM 加密
+// 值的格式為 base64url(nonce || ciphertext)，並以 Cookie 名稱作為附加數據
+pub struct PrivateCookieManager {
+    cookies: CookieManager,
+    ciphers: Vec<Aes256Gcm>,
+}
+
+impl PrivateCookieManager {
+    pub fn new(keys: Vec<Vec<u8>>) -> Self {
+        let ciphers = keys
+            .iter()
+            .map(|key| {
+                // 任意長度的密鑰統一派生為 256 位
+                let key = Sha256::digest(key);
+                Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key))
+            })
+            .collect();
+        Self {
+            cookies: CookieManager::new(),
+            ciphers,
+        }
+    }
+
+    pub fn from_config(config: &SsrkitConfig) -> Self {
+        Self::new(config.get_cookie_encryption_keys())
+    }
+
+    // 只載入能夠成功解密的 Cookie
+    pub fn with_cookie_header(mut self, header: &str) -> Self {
+        for cookie in Cookie::parse_cookie_header(header) {
+            if self.cookies.get(&cookie.name).is_none() {
+                self.add_original(cookie);
+            }
+        }
+        self
+    }
+
+    pub fn add_original(&mut self, mut cookie: Cookie) -> bool {
+        match self.decrypt(&cookie.name, &cookie.value) {
+            Some(value) => {
+                cookie.value = value;
+                self.cookies.add_original(cookie);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn add(&mut self, cookie: Cookie) {
+        self.cookies.add(cookie);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Cookie> {
+        self.cookies.get(name)
+    }
+
+    pub fn delete(&mut self, name: &str) {
+        self.cookies.delete(name);
+    }
+
+    pub fn remove(&mut self, cookie: Cookie) {
+        self.cookies.remove(cookie);
+    }
+
+    pub fn update(&mut self, name: &str, value: String) {
+        self.cookies.update(name, value);
+    }
+
+    pub fn to_header_strings(&self) -> Result<Vec<String>, String> {
+        self.cookies
+            .outgoing()
+            .map(|cookie| {
+                let value = self.encrypt(&cookie.name, &cookie.value)?;
+                Ok(Cookie {
+                    value,
+                    ..cookie.clone()
+                }
+                .to_header_string())
+            })
+            .chain(self.cookies.removed().map(|c| Ok(c.to_header_string())))
+            .collect()
+    }
+
+    fn encrypt(&self, name: &str, value: &str) -> Result<String, String> {
+        let cipher = self
+            .ciphers
+            .first()
+            .ok_or("No cookie encryption key configured")?;
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let payload = Payload {
+            msg: value.as_bytes(),
+            aad: name.as_bytes(),
+        };
+        let ciphertext = cipher
+            .encrypt(&nonce, payload)
+            .map_err(|e| format!("Failed to encrypt cookie '{}': {}", name, e))?;
+        let mut data = nonce.to_vec();
+        data.extend(ciphertext);
+        Ok(URL_SAFE_NO_PAD.encode(data))
+    }
+
+    fn decrypt(&self, name: &str, value: &str) -> Option<String> {
+        let data = URL_SAFE_NO_PAD.decode(value).ok()?;
+        if data.len() < 12 {
+            return None;
+        }
+        let (nonce, ciphertext) = data.split_at(12);
+        self.ciphers.iter().find_map(|cipher| {
+            let payload = Payload {
+                msg: ciphertext,
+                aad: name.as_bytes(),
+            };
+            let plaintext = cipher.decrypt(Nonce::from_slice(nonce), payload).ok()?;
+            String::from_utf8(plaintext).ok()
+        })
+    }
+}
+
+// 保存一次性提示的 Session 鍵
+const FLASH_KEY: &str = "_flash";
+// 保存 CSRF 令牌的 Session 鍵
+const CSRF_KEY: &str = "_csrf_token";
+
+// 一次性提示，如表單提交並重定向後顯示的通知，在下一次渲染時被取出
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FlashMessage {
+    pub level: String,
+    pub message: String,
+}
+
+// 使用牆上時間，使 Session 可以序列化並在進程間比較
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Session {
     pub user_id: String,
-    pub data: HashMap<String, String>,
-    pub last_accessed: Instant,
+    pub data: HashMap<String, Value>,
+    pub created_at: DateTime<Utc>,
+    pub last_accessed: DateTime<Utc>,
 }
 
 impl Session {
     pub fn new(user_id: String) -> Self {
+        Self::new_at(user_id, Utc::now())
+    }
+
+    pub fn new_at(user_id: String, now: DateTime<Utc>) -> Self {
         Self {
             user_id,
             data: HashMap::new(),
-            last_accessed: Instant::now(),
+            created_at: now,
+            last_accessed: now,
         }
     }
 
     pub fn touch(&mut self) {
-        self.last_accessed = Instant::now();
+        self.touch_at(Utc::now());
+    }
+
+    pub fn touch_at(&mut self, now: DateTime<Utc>) {
+        self.last_accessed = now;
+    }
+
+    pub fn is_expired(
+        &self,
+        now: DateTime<Utc>,
+        idle_timeout: std::time::Duration,
+        absolute_timeout: Option<std::time::Duration>,
+    ) -> bool {
+        elapsed_between(self.last_accessed, now) >= idle_timeout
+            || absolute_timeout
+                .is_some_and(|timeout| elapsed_between(self.created_at, now) >= timeout)
+    }
+
+    // 值不存在或無法反序列化為 T 時返回 None
+    pub fn get<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        self.data
+            .get(key)
+            .and_then(|value| serde_json::from_value(value.clone()).ok())
+    }
+
+    pub fn insert<T: Serialize>(&mut self, key: &str, value: T) -> Result<(), String> {
+        let value = serde_json::to_value(value).map_err(|e| e.to_string())?;
+        self.data.insert(key.to_string(), value);
+        Ok(())
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<Value> {
+        self.data.remove(key)
+    }
+
+    pub fn flash(&mut self, level: &str, message: &str) {
+        let mut flashes = self.peek_flashes();
+        flashes.push(FlashMessage {
+            level: level.to_string(),
+            message: message.to_string(),
+        });
+        let _ = self.insert(FLASH_KEY, flashes);
+    }
+
+    pub fn has_flashes(&self) -> bool {
+        self.data.contains_key(FLASH_KEY)
+    }
+
+    // 讀取但不消費
+    pub fn peek_flashes(&self) -> Vec<FlashMessage> {
+        self.get(FLASH_KEY).unwrap_or_default()
+    }
+
+    pub fn csrf_token(&self) -> Option<String> {
+        self.get(CSRF_KEY)
+    }
+
+    // 校驗表單提交的令牌，Session 中沒有令牌時一律拒絕
+    pub fn verify_csrf_token(&self, token: &str) -> bool {
+        self.csrf_token()
+            .is_some_and(|expected| constant_time_eq(expected.as_bytes(), token.as_bytes()))
+    }
+
+    pub fn take_flashes(&mut self) -> Vec<FlashMessage> {
+        self.remove(FLASH_KEY)
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default()
     }
 }
 
-pub struct SessionManager {
+// 從 SessionStore 取出的 Session，離開作用域時寫回存儲
+pub struct SessionRef<'a> {
+    session_id: String,
+    session: Session,
+    store: &'a dyn SessionStore,
+}
+
+impl SessionRef<'_> {
+    pub fn id(&self) -> &str {
+        &self.session_id
+    }
+}
+
+impl Deref for SessionRef<'_> {
+    type Target = Session;
+
+    fn deref(&self) -> &Self::Target {
+        &self.session
+    }
+}
+
+impl DerefMut for SessionRef<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.session
+    }
+}
+
+impl Drop for SessionRef<'_> {
+    fn drop(&mut self) {
+        let _ = self.store.save(&self.session_id, &self.session);
+    }
+}
+
+// 單個請求的 Session：從 Session Cookie 載入，首次寫入時才真正創建
+#[derive(Debug, Default)]
+pub struct RequestSession {
+    session_id: Option<String>,
+    session: Option<Session>,
+    modified: bool,
+}
+
+impl RequestSession {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn id(&self) -> Option<&str> {
+        self.session_id.as_deref()
+    }
+
+    pub fn get(&self) -> Option<&Session> {
+        self.session.as_ref()
+    }
+
+    pub fn get_mut(&mut self) -> &mut Session {
+        self.modified = true;
+        self.session
+            .get_or_insert_with(|| Session::new(String::new()))
+    }
+
+    pub fn is_modified(&self) -> bool {
+        self.modified
+    }
+
+    pub fn verify_csrf_token(&self, token: &str) -> bool {
+        self.session
+            .as_ref()
+            .is_some_and(|session| session.verify_csrf_token(token))
+    }
+}
+
+// 用戶到 Session ID 的索引，每個用戶的 Session 按創建順序排列
+#[derive(Default)]
+struct UserSessionIndex {
+    by_user: HashMap<String, Vec<String>>,
+    by_session: HashMap<String, String>,
+}
+
+impl UserSessionIndex {
+    // 返回是否新增了索引項
+    fn insert(&mut self, session_id: &str, user_id: &str) -> bool {
+        if self.by_session.get(session_id).map(String::as_str) == Some(user_id) {
+            return false;
+        }
+        self.remove(session_id);
+        // 匿名 Session 不建立索引
+        if user_id.is_empty() {
+            return false;
+        }
+        self.by_user
+            .entry(user_id.to_string())
+            .or_default()
+            .push(session_id.to_string());
+        self.by_session
+            .insert(session_id.to_string(), user_id.to_string());
+        true
+    }
+
+    fn replace(&mut self, old_session_id: &str, new_session_id: &str) {
+        let Some(user_id) = self.by_session.remove(old_session_id) else {
+            return;
+        };
+        if let Some(id) = self
+            .by_user
+            .get_mut(&user_id)
+            .and_then(|ids| ids.iter_mut().find(|id| *id == old_session_id))
+        {
+            *id = new_session_id.to_string();
+        }
+        self.by_session.insert(new_session_id.to_string(), user_id);
+    }
+
+    fn remove(&mut self, session_id: &str) {
+        let Some(user_id) = self.by_session.remove(session_id) else {
+            return;
+        };
+        if let Some(ids) = self.by_user.get_mut(&user_id) {
+            ids.retain(|id| id != session_id);
+            if ids.is_empty() {
+                self.by_user.remove(&user_id);
+            }
+        }
+    }
+
+    fn sessions(&self, user_id: &str) -> Vec<String> {
+        self.by_user.get(user_id).cloned().unwrap_or_default()
+    }
+}
+
+// Session 生命週期事件，用於審計登錄或在 Session 結束時清理用戶資源
+#[derive(Debug)]
+pub enum SessionEvent<'a> {
+    Created {
+        session_id: &'a str,
+        session: &'a Session,
+    },
+    Touched {
+        session_id: &'a str,
+        session: &'a Session,
+    },
+    Regenerated {
+        old_session_id: &'a str,
+        new_session_id: &'a str,
+        session: &'a Session,
+    },
+    // 由 get_session、list_user_sessions 或定期清理發現過期
+    Expired {
+        session_id: &'a str,
+        session: &'a Session,
+    },
+    // 被 remove_session、revoke_user_sessions 或每用戶數量上限移除
+    Destroyed {
+        session_id: &'a str,
+        session: &'a Session,
+    },
+}
+
+type SessionHook = Box<dyn Fn(&SessionEvent<'_>) + Send + Sync>;
+
+// 時鐘回撥時視為剛剛發生
+fn elapsed_between(time: DateTime<Utc>, now: DateTime<Utc>) -> std::time::Duration {
+    (now - time).to_std().unwrap_or_default()
+}
+
+#[derive(Serialize, Deserialize)]
+struct SessionSnapshot {
     sessions: HashMap<String, Session>,
+}
+
+pub struct SessionManager {
+    store: Box<dyn SessionStore>,
     session_duration: std::time::Duration,
+    absolute_timeout: Option<std::time::Duration>,
+    user_index: UserSessionIndex,
+    hooks: Vec<SessionHook>,
+    clock: Arc<dyn Clock>,
     config: Arc<SsrkitConfig>,
 }
 
 impl SessionManager {
-    pub fn new(session_duration: std::time::Duration, config: Arc<SsrkitConfig>) -> Self {
+    // 存儲無法創建時（如 Session 目錄不可寫）返回錯誤
+    pub fn new(
+        session_duration: std::time::Duration,
+        config: Arc<SsrkitConfig>,
+    ) -> Result<Self, String> {
+        let store = config.get_session_store().create()?;
+        Ok(Self::with_store(session_duration, config, store))
+    }
+
+    pub fn with_store(
+        session_duration: std::time::Duration,
+        config: Arc<SsrkitConfig>,
+        store: Box<dyn SessionStore>,
+    ) -> Self {
+        // 從已有的 Session 重建用戶索引，使持久化存儲在重啟後仍能按用戶查找
+        let mut sessions = Vec::new();
+        let _ = store.retain(&mut |session_id, session| {
+            sessions.push((
+                session.created_at,
+                session_id.to_string(),
+                session.user_id.clone(),
+            ));
+            true
+        });
+        sessions.sort();
+        let mut user_index = UserSessionIndex::default();
+        for (_, session_id, user_id) in sessions {
+            user_index.insert(&session_id, &user_id);
+        }
+
         Self {
-            sessions: HashMap::new(),
+            store,
             session_duration,
+            absolute_timeout: config.get_session_absolute_timeout(),
+            user_index,
+            hooks: Vec::new(),
+            clock: Arc::new(SystemClock),
             config,
         }
     }
 
-    pub fn create_session(&mut self, user_id: String) -> String {
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    fn is_session_expired(&self, session: &Session) -> bool {
+        session.is_expired(
+            self.clock.now(),
+            self.session_duration,
+            self.absolute_timeout,
+        )
+    }
+
+    pub fn on_event<F>(&mut self, hook: F)
+    where
+        F: Fn(&SessionEvent<'_>) + Send + Sync + 'static,
+    {
+        self.hooks.push(Box::new(hook));
+    }
+
+    fn emit(&self, event: SessionEvent<'_>) {
+        for hook in &self.hooks {
+            hook(&event);
+        }
+    }
+
+    fn generate_session_id(&self) -> String {
         let length = self.config.get_nanoid_length();
         let alphabet = self.config.get_nanoid_alphabet();
-        let session_id = nanoid!(length, &alphabet);
-        self.sessions
-            .insert(session_id.clone(), Session::new(user_id));
-        session_id
-    }
-
-    pub fn get_session(&mut self, session_id: &str) -> Option<&mut Session> {
-        let duration = self.session_duration;
-        self.sessions.get_mut(session_id).and_then(|session| {
-            if session.last_accessed.elapsed() < duration {
-                session.touch();
-                Some(session)
-            } else {
-                None
+        nanoid!(length, &alphabet)
+    }
+
+    // 存儲失敗時 panic，需要處理錯誤時使用 try_create_session
+    pub fn create_session(&mut self, user_id: String) -> String {
+        self.try_create_session(user_id)
+            .expect("Failed to save session")
+    }
+
+    pub fn try_create_session(&mut self, user_id: String) -> Result<String, String> {
+        let session_id = self.generate_session_id();
+        let session = Session::new_at(user_id, self.clock.now());
+        self.store.save(&session_id, &session)?;
+        self.index_session(&session_id, &session.user_id);
+        self.emit(SessionEvent::Created {
+            session_id: &session_id,
+            session: &session,
+        });
+        Ok(session_id)
+    }
+
+    // 記錄 Session 所屬用戶，並按 session_max_per_user 淘汰該用戶最早的 Session
+    fn index_session(&mut self, session_id: &str, user_id: &str) {
+        if !self.user_index.insert(session_id, user_id) {
+            return;
+        }
+        let Some(max) = self.config.get_session_max_per_user() else {
+            return;
+        };
+        let sessions = self.list_user_sessions(user_id);
+        let excess = sessions.len().saturating_sub(max);
+        for (old_session_id, _) in sessions.into_iter().take(excess) {
+            self.remove_session(&old_session_id);
+        }
+    }
+
+    // 列出用戶當前有效的 Session，按創建順序排列，不會刷新訪問時間
+    pub fn list_user_sessions(&mut self, user_id: &str) -> Vec<(String, Session)> {
+        let mut sessions = Vec::new();
+        for session_id in self.user_index.sessions(user_id) {
+            match self.store.load(&session_id).ok().flatten() {
+                Some(session) if session.user_id == user_id => {
+                    if self.is_session_expired(&session) {
+                        self.expire_session(&session_id, &session);
+                    } else {
+                        sessions.push((session_id, session));
+                    }
+                }
+                _ => self.user_index.remove(&session_id),
             }
+        }
+        sessions
+    }
+
+    // 使用戶的所有 Session 失效，如「在所有設備上登出」，返回移除的數量
+    pub fn revoke_user_sessions(&mut self, user_id: &str) -> usize {
+        let session_ids = self.user_index.sessions(user_id);
+        for session_id in &session_ids {
+            self.remove_session(session_id);
+        }
+        session_ids.len()
+    }
+
+    // 把 Session 數據移到新的 ID 並使舊 ID 失效，用於登錄或權限變更後防止 Session 固定攻擊
+    pub fn regenerate_session(&mut self, session_id: &str) -> Option<String> {
+        let mut session = self.get_session(session_id)?.clone();
+        // 權限變更後舊頁面上的 CSRF 令牌一併失效
+        session.remove(CSRF_KEY);
+        let new_session_id = self.generate_session_id();
+        self.store.save(&new_session_id, &session).ok()?;
+        let _ = self.store.remove(session_id);
+        self.user_index.replace(session_id, &new_session_id);
+        self.emit(SessionEvent::Regenerated {
+            old_session_id: session_id,
+            new_session_id: &new_session_id,
+            session: &session,
+        });
+        Some(new_session_id)
+    }
+
+    // 同上，並在本次響應中更新保存 Session ID 的 Cookie
+    pub fn regenerate_session_cookie(
+        &mut self,
+        cookies: &mut CookieManager,
+        cookie_name: &str,
+    ) -> Option<String> {
+        let session_id = cookies.get(cookie_name)?.value.clone();
+        let new_session_id = self.regenerate_session(&session_id)?;
+        cookies.add(Cookie {
+            name: cookie_name.to_string(),
+            ..self.session_cookie(&new_session_id)
+        });
+        Some(new_session_id)
+    }
+
+    // 在請求處理過程中重新生成 Session ID（如登錄後），保存請求中的修改並更新 Cookie，
+    // 之後的 commit_request_session 寫入新 ID 而不會重新創建舊 ID；尚未保存的 Session 無需處理
+    pub fn regenerate_request_session(
+        &mut self,
+        request_session: &mut RequestSession,
+        cookies: &mut CookieManager,
+    ) -> Option<String> {
+        let old_session_id = request_session.session_id.clone()?;
+        let session = request_session.session.as_mut()?;
+        let new_session_id = self.generate_session_id();
+        let mut regenerated = session.clone();
+        regenerated.remove(CSRF_KEY);
+        self.store.save(&new_session_id, &regenerated).ok()?;
+        let _ = self.store.remove(&old_session_id);
+        *session = regenerated;
+        self.user_index.replace(&old_session_id, &new_session_id);
+        self.index_session(&new_session_id, &session.user_id);
+        self.emit(SessionEvent::Regenerated {
+            old_session_id: &old_session_id,
+            new_session_id: &new_session_id,
+            session,
+        });
+        cookies.add(self.session_cookie(&new_session_id));
+        request_session.session_id = Some(new_session_id.clone());
+        request_session.modified = false;
+        Some(new_session_id)
+    }
+
+    // 返回請求 Session 的 CSRF 令牌，沒有時使用配置的 nanoid 字母表生成並寫入 Session
+    pub fn csrf_token(&self, request_session: &mut RequestSession) -> String {
+        if let Some(token) = request_session.get().and_then(Session::csrf_token) {
+            return token;
+        }
+        let token = self.generate_session_id();
+        let _ = request_session.get_mut().insert(CSRF_KEY, &token);
+        token
+    }
+
+    // Session Cookie 的默認屬性：HttpOnly、SameSite=Lax、Path=/
+    pub fn session_cookie(&self, session_id: &str) -> Cookie {
+        let mut cookie = Cookie::new(
+            self.config.get_session_cookie_name(),
+            session_id.to_string(),
+        );
+        cookie.path = Some("/".to_string());
+        cookie.http_only = true;
+        cookie.same_site = Some(SameSite::Lax);
+        cookie.secure = self.config.get_session_cookie_secure();
+        cookie
+    }
+
+    pub fn load_request_session(&mut self, cookies: &CookieManager) -> RequestSession {
+        let Some(cookie) = cookies.get(&self.config.get_session_cookie_name()) else {
+            return RequestSession::new();
+        };
+        let session_id = cookie.value.clone();
+        match self.get_session(&session_id).map(|session| session.clone()) {
+            Some(session) => RequestSession {
+                session_id: Some(session_id),
+                session: Some(session),
+                modified: false,
+            },
+            None => RequestSession::new(),
+        }
+    }
+
+    // 保存被修改過的 Session，新建的 Session 同時寫入 Session Cookie；已失效的 Session 的修改會被丟棄
+    pub fn commit_request_session(
+        &mut self,
+        request_session: &mut RequestSession,
+        cookies: &mut CookieManager,
+    ) -> Result<(), String> {
+        if !request_session.modified {
+            return Ok(());
+        }
+        let Some(session) = request_session.session.as_mut() else {
+            return Ok(());
+        };
+        let (session_id, created) = match &request_session.session_id {
+            Some(session_id) => {
+                // 請求處理期間 Session 可能已被撤銷、過期或重新生成，不能重新寫入舊 ID
+                match self.store.load(session_id)? {
+                    Some(stored) if !self.is_session_expired(&stored) => {}
+                    stored => {
+                        if let Some(stored) = stored {
+                            self.expire_session(session_id, &stored);
+                        }
+                        *request_session = RequestSession::new();
+                        return Ok(());
+                    }
+                }
+                (session_id.clone(), false)
+            }
+            None => {
+                let session_id = self.generate_session_id();
+                cookies.add(self.session_cookie(&session_id));
+                let now = self.clock.now();
+                session.created_at = now;
+                session.last_accessed = now;
+                (session_id, true)
+            }
+        };
+        self.store.save(&session_id, session)?;
+        self.index_session(&session_id, &session.user_id);
+        if created {
+            self.emit(SessionEvent::Created {
+                session_id: &session_id,
+                session,
+            });
+        }
+        request_session.session_id = Some(session_id);
+        request_session.modified = false;
+        Ok(())
+    }
+
+    pub fn get_session(&mut self, session_id: &str) -> Option<SessionRef<'_>> {
+        let mut session = self.store.load(session_id).ok().flatten()?;
+        if self.is_session_expired(&session) {
+            self.expire_session(session_id, &session);
+            return None;
+        }
+        session.touch_at(self.clock.now());
+        self.emit(SessionEvent::Touched {
+            session_id,
+            session: &session,
+        });
+        Some(SessionRef {
+            session_id: session_id.to_string(),
+            session,
+            store: self.store.as_ref(),
         })
     }
 
     pub fn remove_session(&mut self, session_id: &str) {
-        self.sessions.remove(session_id);
+        // 沒有註冊鉤子時無需讀取被移除的 Session
+        let session = if self.hooks.is_empty() {
+            None
+        } else {
+            self.store.load(session_id).ok().flatten()
+        };
+        let _ = self.store.remove(session_id);
+        self.user_index.remove(session_id);
+        if let Some(session) = session {
+            self.emit(SessionEvent::Destroyed {
+                session_id,
+                session: &session,
+            });
+        }
+    }
+
+    fn expire_session(&mut self, session_id: &str, session: &Session) {
+        let _ = self.store.remove(session_id);
+        self.user_index.remove(session_id);
+        self.emit(SessionEvent::Expired {
+            session_id,
+            session,
+        });
+    }
+
+    // 把所有未過期的 Session 寫入快照文件，用於平滑重啟
+    pub fn save_snapshot(&self, path: impl AsRef<Path>) -> Result<(), String> {
+        let path = path.as_ref();
+        let (idle_timeout, absolute_timeout) = (self.session_duration, self.absolute_timeout);
+        let now = self.clock.now();
+        let mut sessions = HashMap::new();
+        self.store.retain(&mut |session_id, session| {
+            if !session.is_expired(now, idle_timeout, absolute_timeout) {
+                sessions.insert(session_id.to_string(), session.clone());
+            }
+            true
+        })?;
+        let content =
+            serde_json::to_string(&SessionSnapshot { sessions }).map_err(|e| e.to_string())?;
+        let tmp_path = path.with_extension("tmp");
+        std::fs::write(&tmp_path, content)
+            .and_then(|_| std::fs::rename(&tmp_path, path))
+            .map_err(|e| format!("Failed to write session snapshot {:?}: {}", path, e))
+    }
+
+    // 從快照文件恢復 Session，跳過已過期的，返回恢復的數量
+    pub fn load_snapshot(&mut self, path: impl AsRef<Path>) -> Result<usize, String> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read session snapshot {:?}: {}", path, e))?;
+        let snapshot: SessionSnapshot = serde_json::from_str(&content)
+            .map_err(|e| format!("Failed to parse session snapshot {:?}: {}", path, e))?;
+        let mut sessions: Vec<_> = snapshot
+            .sessions
+            .into_iter()
+            .filter(|(_, session)| !self.is_session_expired(session))
+            .collect();
+        sessions.sort_by_key(|(_, session)| session.created_at);
+        for (session_id, session) in &sessions {
+            self.store.save(session_id, session)?;
+            self.user_index.insert(session_id, &session.user_id);
+        }
+        Ok(sessions.len())
     }
 
     pub fn cleanup_expired_sessions(&mut self) {
-        self.sessions
-            .retain(|_, session| session.last_accessed.elapsed() < self.session_duration);
+        let (idle_timeout, absolute_timeout) = (self.session_duration, self.absolute_timeout);
+        let now = self.clock.now();
+        let mut expired = Vec::new();
+        let _ = self.store.retain(&mut |session_id, session| {
+            let keep = !session.is_expired(now, idle_timeout, absolute_timeout);
+            if !keep {
+                expired.push((session_id.to_string(), session.clone()));
+            }
+            keep
+        });
+        for (session_id, session) in expired {
+            self.user_index.remove(&session_id);
+            self.emit(SessionEvent::Expired {
+                session_id: &session_id,
+                session: &session,
+            });
+        }
+    }
+}
+
+// 後台定期清理過期 Session 的線程，停止或被 drop 時退出
+pub struct SessionReaper {
+    stop: Option<mpsc::Sender<()>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl SessionReaper {
+    pub fn start(
+        session_manager: &Arc<RwLock<SessionManager>>,
+        interval: std::time::Duration,
+    ) -> Self {
+        let (stop, stopped) = mpsc::channel::<()>();
+        let session_manager = Arc::downgrade(session_manager);
+        let handle = thread::spawn(move || {
+            // 收到停止信號或發送端被 drop 時退出
+            while let Err(mpsc::RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                let Some(session_manager) = session_manager.upgrade() else {
+                    break;
+                };
+                if let Ok(mut guard) = session_manager.write() {
+                    guard.cleanup_expired_sessions();
+                };
+            }
+        });
+        Self {
+            stop: Some(stop),
+            handle: Some(handle),
+        }
+    }
+
+    pub fn stop(&mut self) {
+        drop(self.stop.take());
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for SessionReaper {
+    fn drop(&mut self) {
+        self.stop();
     }
 }
 
 pub struct GlobalState {
     pub cache: Arc<Cache<String>>,
-    pub cookie_manager: Arc<Mutex<CookieManager>>,
     pub session_manager: Arc<RwLock<SessionManager>>,
     pub config: Arc<SsrkitConfig>,
+    pub clock: Arc<dyn Clock>,
+    session_reaper: Option<SessionReaper>,
 }
 
 impl GlobalState {
@@ -192,27 +1458,37 @@ impl GlobalState {
         cache: Cache<String>,
         config: SsrkitConfig,
         session_duration: std::time::Duration,
-    ) -> Self {
+    ) -> Result<Self, String> {
+        Self::with_clock(cache, config, session_duration, Arc::new(SystemClock))
+    }
+
+    // 使用指定的時鐘，測試中可傳入 ManualClock 來驗證過期邏輯而無需等待
+    pub fn with_clock(
+        cache: Cache<String>,
+        config: SsrkitConfig,
+        session_duration: std::time::Duration,
+        clock: Arc<dyn Clock>,
+    ) -> Result<Self, String> {
         let config = Arc::new(config);
-        Self {
-            cache: Arc::new(cache),
-            cookie_manager: Arc::new(Mutex::new(CookieManager::new())),
-            session_manager: Arc::new(RwLock::new(SessionManager::new(
-                session_duration,
-                config.clone(),
-            ))),
+        let session_manager = Arc::new(RwLock::new(
+            SessionManager::new(session_duration, config.clone())?.with_clock(clock.clone()),
+        ));
+        let session_reaper = config
+            .get_session_cleanup_interval()
+            .map(|interval| SessionReaper::start(&session_manager, interval));
+        Ok(Self {
+            cache: Arc::new(cache.with_clock(clock.clone())),
+            session_manager,
             config,
-        }
+            clock,
+            session_reaper,
+        })
     }
 
     pub fn get_cache(&self) -> &Arc<Cache<String>> {
         &self.cache
     }
 
-    pub fn get_cookie_manager(&self) -> &Arc<Mutex<CookieManager>> {
-        &self.cookie_manager
-    }
-
     pub fn get_session_manager(&self) -> &Arc<RwLock<SessionManager>> {
         &self.session_manager
     }
@@ -220,6 +1496,19 @@ impl GlobalState {
     pub fn get_config(&self) -> &Arc<SsrkitConfig> {
         &self.config
     }
+
+    pub fn get_clock(&self) -> &Arc<dyn Clock> {
+        &self.clock
+    }
+
+    // 使用全局時鐘和配置的簽名密鑰創建本次請求的 Cookie 容器
+    pub fn cookie_manager(&self) -> CookieManager {
+        CookieManager::from_config(&self.config).with_clock(self.clock.clone())
+    }
+
+    pub fn has_session_reaper(&self) -> bool {
+        self.session_reaper.is_some()
+    }
 }
 
 // 全局静态变量
@@ -230,18 +1519,29 @@ pub fn init_global_state(
     cache: Cache<String>,
     config: SsrkitConfig,
     session_duration: std::time::Duration,
-) {
-    let _ = GLOBAL_STATE.set(RwLock::new(GlobalState::new(
-        cache,
-        config,
-        session_duration,
-    )));
+) -> Result<(), String> {
+    let state = GlobalState::new(cache, config, session_duration)?;
+    let _ = GLOBAL_STATE.set(RwLock::new(state));
+    Ok(())
 }
 
 pub fn get_global_state() -> &'static RwLock<GlobalState> {
     GLOBAL_STATE.get().expect("Global state not initialized")
 }
 
+// 全局狀態尚未初始化時（如單獨測試模板或渲染器）使用系統時鐘
+pub(crate) fn global_clock() -> Arc<dyn Clock> {
+    GLOBAL_STATE
+        .get()
+        .and_then(|state| state.read().ok().map(|state| state.clock.clone()))
+        .unwrap_or_else(|| Arc::new(SystemClock))
+}
+
+pub(crate) fn global_cookie_manager() -> Option<CookieManager> {
+    let state = GLOBAL_STATE.get()?.read().ok()?;
+    Some(state.cookie_manager())
+}
+
 pub fn set_global_state(new_state: GlobalState) -> Result<(), String> {
     match GLOBAL_STATE.get() {
         Some(lock) => {
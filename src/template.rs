@@ -1,3 +1,4 @@
+use crate::state::global_clock;
 use crate::Cache;
 use serde_json::Value;
 use std::sync::OnceLock;
@@ -171,8 +172,14 @@ impl Template {
     }
 }
 
+// 緩存過期使用全局狀態的時鐘，應在 init_global_state 之後調用
 pub fn init_template_cache() {
-    TEMPLATE_CACHE.get_or_init(|| Cache::new(|config| config.get_template_cache_size()));
+    TEMPLATE_CACHE.get_or_init(|| {
+        Cache::new(|config| config.get_template_cache_size())
+            .with_ttl(|config| config.get_template_cache_ttl())
+            .with_shards(|config| config.get_template_cache_shards())
+            .with_clock(global_clock())
+    });
 }
 
 pub fn render_template<F>(key: &str, render_fn: F) -> String
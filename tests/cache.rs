@@ -1,5 +1,6 @@
 use ssrkit::prelude::*;
 use std::num::NonZeroUsize;
+use std::time::Duration;
 
 #[test]
 fn test_cache_insert_and_get() {
@@ -21,3 +22,176 @@ fn test_cache_get_or_insert() {
     assert_eq!(result, value);
     assert_eq!(cache.get(key), Some(value));
 }
+
+#[test]
+fn test_cache_entry_ttl() {
+    // 測試默認有效期和單條有效期，過期條目視為未命中
+    let clock = std::sync::Arc::new(ManualClock::default());
+    let cache = Cache::new(|_config| NonZeroUsize::new(10).unwrap())
+        .with_ttl(|_config| Some(Duration::from_secs(60)))
+        .with_clock(clock.clone());
+    cache.insert("page", "v1");
+    cache.insert_with_ttl("island", "v1", Duration::from_secs(5));
+
+    clock.advance(Duration::from_secs(5));
+    assert_eq!(cache.get("page"), Some("v1"));
+    assert_eq!(cache.get("island"), None);
+    assert_eq!(cache.get_or_insert("island", || "v2"), "v2");
+
+    clock.advance(Duration::from_secs(55));
+    assert_eq!(cache.get("page"), None);
+    assert_eq!(cache.get("island"), Some("v2"));
+}
+
+#[test]
+fn test_cache_stale_while_revalidate() {
+    // 測試過期條目在容忍期內立即返回並在後台刷新，超過容忍期後同步重新生成
+    let clock = std::sync::Arc::new(ManualClock::default());
+    let cache = Cache::new(|_config| NonZeroUsize::new(10).unwrap())
+        .with_ttl(|_config| Some(Duration::from_secs(60)))
+        .with_stale_while_revalidate(Duration::from_secs(30))
+        .with_clock(clock.clone());
+    cache.insert("page", "v1".to_string());
+
+    clock.advance(Duration::from_secs(70));
+    assert_eq!(cache.get("page"), None);
+    assert_eq!(cache.get_or_revalidate("page", || "v2".to_string()), "v1");
+    let mut refreshed = None;
+    for _ in 0..100 {
+        refreshed = cache.get("page");
+        if refreshed.is_some() {
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(refreshed.as_deref(), Some("v2"));
+
+    clock.advance(Duration::from_secs(91));
+    assert_eq!(cache.get_or_revalidate("page", || "v3".to_string()), "v3");
+
+    // 容忍期只對 get_or_revalidate 有效
+    clock.advance(Duration::from_secs(70));
+    assert_eq!(cache.get_or_insert("page", || "v4".to_string()), "v4");
+
+    // 後台刷新沿用 insert_with_ttl 的有效期，而不是沒有默認有效期時永不過期
+    let cache = Cache::new(|_config| NonZeroUsize::new(10).unwrap())
+        .with_stale_while_revalidate(Duration::from_secs(30))
+        .with_clock(clock.clone());
+    cache.insert_with_ttl("island", "v1".to_string(), Duration::from_secs(10));
+    clock.advance(Duration::from_secs(11));
+    assert_eq!(cache.get_or_revalidate("island", || "v2".to_string()), "v1");
+    let mut refreshed = None;
+    for _ in 0..100 {
+        refreshed = cache.get("island");
+        if refreshed.is_some() {
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(refreshed.as_deref(), Some("v2"));
+    clock.advance(Duration::from_secs(11));
+    assert_eq!(cache.get("island"), None);
+}
+
+#[test]
+fn test_cache_single_flight() {
+    // 測試同一個鍵的並發請求只計算一次，成功結果和錯誤都共享給所有等待者
+    let cache = std::sync::Arc::new(Cache::new(|_config| NonZeroUsize::new(10).unwrap()));
+    let calls = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let barrier = std::sync::Arc::new(std::sync::Barrier::new(8));
+
+    let handles: Vec<_> = (0..8)
+        .map(|_| {
+            let (cache, calls, barrier) = (cache.clone(), calls.clone(), barrier.clone());
+            std::thread::spawn(move || {
+                barrier.wait();
+                let failed = cache.get_or_try_insert("broken", || {
+                    calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                    std::thread::sleep(Duration::from_millis(50));
+                    Err::<String, _>("render failed".to_string())
+                });
+                barrier.wait();
+                let page = cache.get_or_insert("page", || {
+                    calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                    std::thread::sleep(Duration::from_millis(50));
+                    "html".to_string()
+                });
+                (failed, page)
+            })
+        })
+        .collect();
+
+    for handle in handles {
+        let (failed, page) = handle.join().unwrap();
+        assert_eq!(failed, Err("render failed".to_string()));
+        assert_eq!(page, "html");
+    }
+    assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 2);
+    assert_eq!(cache.get("broken"), None);
+}
+
+#[test]
+fn test_cache_negative_ttl() {
+    // 測試錯誤默認不緩存，開啟負緩存後在有效期內直接返回錯誤
+    let clock = std::sync::Arc::new(ManualClock::default());
+    let cache = Cache::new(|_config| NonZeroUsize::new(10).unwrap()).with_clock(clock.clone());
+    let missing = || Err::<String, _>("island not found".to_string());
+    assert!(cache.get_or_try_insert("island", missing).is_err());
+    assert_eq!(
+        cache.get_or_try_insert("island", || Ok("html".to_string())),
+        Ok("html".to_string())
+    );
+
+    let cache = Cache::new(|_config| NonZeroUsize::new(10).unwrap())
+        .with_negative_ttl(Duration::from_secs(5))
+        .with_clock(clock.clone());
+    let calls = std::cell::Cell::new(0);
+    let render = || {
+        calls.set(calls.get() + 1);
+        Err::<String, _>("island not found".to_string())
+    };
+    for _ in 0..3 {
+        assert_eq!(
+            cache.get_or_try_insert("island", render),
+            Err("island not found".to_string())
+        );
+    }
+    assert_eq!(calls.get(), 1);
+
+    clock.advance(Duration::from_secs(5));
+    assert_eq!(
+        cache.get_or_try_insert("island", || Ok("html".to_string())),
+        Ok("html".to_string())
+    );
+}
+
+#[test]
+fn test_sharded_cache() {
+    // 測試分片緩存的總容量與配置一致，且鍵分佈到多個分片
+    let cache = Cache::new(|_config| NonZeroUsize::new(10).unwrap())
+        .with_shards(|_config| NonZeroUsize::new(4).unwrap());
+    for i in 0..1000 {
+        cache.insert(&format!("key-{}", i), i);
+    }
+    // 每個分片都被填滿時才能保留 10 個條目；鍵只落在一個分片時最多保留 3 個
+    let retained = (0..1000)
+        .filter(|i| cache.get(&format!("key-{}", i)) == Some(*i))
+        .count();
+    assert_eq!(retained, 10);
+
+    // 分片數大於容量時按容量截斷，每個分片至少保留一個條目
+    let cache = Cache::new(|_config| NonZeroUsize::new(3).unwrap())
+        .with_shards(|_config| NonZeroUsize::new(16).unwrap());
+    for i in 0..100 {
+        cache.insert(&format!("key-{}", i), i);
+    }
+    let retained = (0..100)
+        .filter(|i| cache.get(&format!("key-{}", i)).is_some())
+        .count();
+    assert_eq!(retained, 3);
+
+    let cache = Cache::new(|config| config.get_global_state_cache_size())
+        .with_shards(|config| config.get_global_state_cache_shards());
+    assert_eq!(cache.get_or_insert("key-0", || 42), 42);
+    assert_eq!(cache.get("key-0"), Some(42));
+}
@@ -0,0 +1,43 @@
+use ssrkit::prelude::*;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[test]
+fn test_global_clock_drives_template_cache() {
+    // 測試模板緩存使用全局狀態的時鐘判斷過期
+    let config = SsrkitConfig::change()
+        .template_cache_ttl(Duration::from_secs(60))
+        .finish();
+    ssrkit::config::set_global_config(config.clone());
+    init_cache(&config);
+    let cache = Cache::new(|config| config.get_global_state_cache_size());
+    init_global_state(cache, config.clone(), Duration::from_secs(3600)).unwrap();
+    let clock = Arc::new(ManualClock::default());
+    ssrkit::state::set_global_state(
+        GlobalState::with_clock(
+            Cache::new(|config| config.get_global_state_cache_size()),
+            config,
+            Duration::from_secs(3600),
+            clock.clone(),
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    ssrkit::template::init_template_cache();
+
+    let renders = AtomicUsize::new(0);
+    let render = || {
+        ssrkit::template::render_template("page", || {
+            renders.fetch_add(1, Ordering::SeqCst);
+            "<html></html>".to_string()
+        })
+    };
+    render();
+    clock.advance(Duration::from_secs(59));
+    render();
+    assert_eq!(renders.load(Ordering::SeqCst), 1);
+    clock.advance(Duration::from_secs(2));
+    render();
+    assert_eq!(renders.load(Ordering::SeqCst), 2);
+}
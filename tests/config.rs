@@ -11,6 +11,7 @@ fn test_config_change() {
         .global_state_cache_size(NonZeroUsize::new(500).unwrap())
         .global_state_session_duration(Duration::from_secs(1800))
         .template_cache_size(NonZeroUsize::new(50).unwrap())
+        .template_cache_shards(NonZeroUsize::new(4).unwrap())
         .finish();
 
     assert_eq!(config.get_nanoid_length(), 10);
@@ -24,4 +25,6 @@ fn test_config_change() {
         Duration::from_secs(1800)
     );
     assert_eq!(config.get_template_cache_size().get(), 50);
+    assert_eq!(config.get_template_cache_shards().get(), 4);
+    assert_eq!(config.get_global_state_cache_shards().get(), 1);
 }
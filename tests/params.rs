@@ -24,3 +24,199 @@ fn test_combined_params_processor() {
 
     assert_eq!(result.get("key"), Some(&Value::String("value".to_string())));
 }
+
+#[test]
+fn test_dynamic_route_patterns() {
+    // 測試動態路由參數的捕獲
+    struct EchoProcessor;
+    impl ParamsProcessor for EchoProcessor {
+        fn process(
+            &self,
+            _path: &str,
+            params: &HashMap<String, String>,
+        ) -> serde_json::Map<String, Value> {
+            params
+                .iter()
+                .map(|(k, v)| (k.clone(), Value::String(v.clone())))
+                .collect()
+        }
+    }
+
+    let processor = CombinedParamsProcessor::new()
+        .add("/blog/[id]", EchoProcessor)
+        .add("/docs/[...slug]", EchoProcessor)
+        .add("/[[lang]]/about", EchoProcessor);
+
+    let params = HashMap::from([("page".to_string(), "2".to_string())]);
+    let result = processor.process("/blog/42?draft=1", &params);
+    assert_eq!(result.get("id"), Some(&Value::String("42".to_string())));
+    assert_eq!(result.get("page"), Some(&Value::String("2".to_string())));
+
+    let result = processor.process("/docs/guide/install", &HashMap::new());
+    assert_eq!(
+        result.get("slug"),
+        Some(&Value::String("guide/install".to_string()))
+    );
+
+    let result = processor.process("/en/about", &HashMap::new());
+    assert_eq!(result.get("lang"), Some(&Value::String("en".to_string())));
+    let result = processor.process("/about", &HashMap::new());
+    assert!(result.get("lang").is_none());
+}
+
+#[test]
+fn test_route_pattern_matches() {
+    // 測試路由模式的匹配規則
+    assert!(RoutePattern::new("/docs/[...slug]")
+        .matches("/docs")
+        .is_none());
+    let captured = RoutePattern::new("/docs/[[...slug]]")
+        .matches("/docs")
+        .unwrap();
+    assert!(captured.is_empty());
+
+    let captured = RoutePattern::new("/files/[...path]/edit")
+        .matches("/files/a/b/edit")
+        .unwrap();
+    assert_eq!(captured.get("path"), Some(&"a/b".to_string()));
+}
+
+#[test]
+fn test_most_specific_route_wins() {
+    // 測試最具體的路由優先，且按路徑段邊界匹配
+    struct NamedProcessor(&'static str);
+    impl ParamsProcessor for NamedProcessor {
+        fn process(
+            &self,
+            _path: &str,
+            _params: &HashMap<String, String>,
+        ) -> serde_json::Map<String, Value> {
+            serde_json::Map::from_iter([("route".to_string(), Value::from(self.0))])
+        }
+    }
+
+    let processor = CombinedParamsProcessor::new()
+        .add("/blog", NamedProcessor("blog"))
+        .add("/blog/[...rest]", NamedProcessor("catch-all"))
+        .add("/blog/[id]", NamedProcessor("post"))
+        .add("/blog/admin", NamedProcessor("admin"))
+        .add("/user", NamedProcessor("user"));
+
+    let route_of = |path: &str| processor.process(path, &HashMap::new())["route"].clone();
+    assert_eq!(route_of("/blog/admin"), "admin");
+    assert_eq!(route_of("/blog/42"), "post");
+    assert_eq!(route_of("/blog/42/comments"), "catch-all");
+    assert_eq!(route_of("/blog"), "blog");
+    assert!(processor.process("/users", &HashMap::new()).is_empty());
+    assert!(processor.validate().is_ok());
+}
+
+#[test]
+fn test_dynamic_segment_does_not_shadow_catch_all() {
+    // 測試同一父路徑下的動態段只匹配單個路徑段，多段路徑交給捕獲全部路由
+    struct NamedProcessor(&'static str);
+    impl ParamsProcessor for NamedProcessor {
+        fn process(
+            &self,
+            _path: &str,
+            params: &HashMap<String, String>,
+        ) -> serde_json::Map<String, Value> {
+            let mut result: serde_json::Map<String, Value> = params
+                .iter()
+                .map(|(k, v)| (k.clone(), Value::String(v.clone())))
+                .collect();
+            result.insert("route".to_string(), Value::from(self.0));
+            result
+        }
+    }
+
+    let processor = CombinedParamsProcessor::new()
+        .add("/blog/[id]", NamedProcessor("post"))
+        .add("/blog/[...slug]", NamedProcessor("slug"));
+
+    let result = processor.process("/blog/42", &HashMap::new());
+    assert_eq!(result["route"], "post");
+    assert_eq!(result["id"], "42");
+
+    let result = processor.process("/blog/a/b", &HashMap::new());
+    assert_eq!(result["route"], "slug");
+    assert_eq!(result["slug"], "a/b");
+    assert!(result.get("id").is_none());
+
+    assert!(RoutePattern::new("/blog/[id]")
+        .matches("/blog/a/b")
+        .is_none());
+    assert!(RoutePattern::new("/user").matches("/user/42").is_none());
+}
+
+#[test]
+fn test_ambiguous_routes_are_reported() {
+    // 測試初始化時報告無法區分的路由
+    struct EmptyProcessor;
+    impl ParamsProcessor for EmptyProcessor {
+        fn process(
+            &self,
+            _path: &str,
+            _params: &HashMap<String, String>,
+        ) -> serde_json::Map<String, Value> {
+            serde_json::Map::new()
+        }
+    }
+
+    let processor = CombinedParamsProcessor::new()
+        .add("/blog/[id]", EmptyProcessor)
+        .add("/blog/[slug]", EmptyProcessor);
+    let err = processor.validate().unwrap_err();
+    assert!(err.contains("/blog/[id]") && err.contains("/blog/[slug]"));
+
+    // 較具體的 [[lang]]/about 在 /about 上被 [id] 搶走
+    let processor = CombinedParamsProcessor::new()
+        .add("/[id]", EmptyProcessor)
+        .add("/[[lang]]/about", EmptyProcessor);
+    let err = processor.validate().unwrap_err();
+    assert!(err.contains("/[id]") && err.contains("/[[lang]]/about") && err.contains("'/about'"));
+
+    // 捕獲全部段仍能匹配多段路徑，屬於有意的回退
+    let processor = CombinedParamsProcessor::new()
+        .add("/blog/[id]", EmptyProcessor)
+        .add("/blog/[...slug]", EmptyProcessor);
+    assert!(processor.validate().is_ok());
+
+    let processor = CombinedParamsProcessor::new()
+        .add("/[id]/[...rest]", EmptyProcessor)
+        .add("/[...all]/[id]", EmptyProcessor);
+    let err = processor.validate().unwrap_err();
+    assert!(err.contains("/[...all]/[id]"));
+
+    let processor = CombinedParamsProcessor::new()
+        .add("/docs/[[...slug]]", EmptyProcessor)
+        .add("/docs/[...slug]", EmptyProcessor)
+        .add("/docs", EmptyProcessor);
+    let err = processor.validate().unwrap_err();
+    assert!(err.contains("Route '/docs' is unreachable"));
+
+    // 路由衝突按段檢查，較深的靜態路由與多個動態段、捕獲全部段並存時也能很快完成校驗
+    let deep = (0..24).map(|i| format!("/s{}", i)).collect::<String>();
+    let processor = CombinedParamsProcessor::new()
+        .add(&deep, EmptyProcessor)
+        .add("/[x]/[y]/[...rest]", EmptyProcessor)
+        .add("/[[a]]/[[b]]/[[...rest]]", EmptyProcessor);
+    let started = std::time::Instant::now();
+    assert!(processor.validate().is_ok());
+    assert!(started.elapsed() < std::time::Duration::from_secs(1));
+
+    let processor = CombinedParamsProcessor::new().add("/[id]/[id]", EmptyProcessor);
+    assert!(processor.validate().is_err());
+
+    let result = SsrInitializer::changer()
+        .params_processor_init(|| {
+            Box::new(
+                CombinedParamsProcessor::new()
+                    .add("/a/[x]", EmptyProcessor)
+                    .add("/a/[y]", EmptyProcessor),
+            )
+        })
+        .finish()
+        .try_init();
+    assert!(result.is_err());
+}
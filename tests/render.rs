@@ -15,7 +15,7 @@ fn test_render() {
     // 初始化全局狀態
     let cache = Cache::new(|config| config.get_global_state_cache_size());
     let session_duration = std::time::Duration::from_secs(3600);
-    init_global_state(cache, config, session_duration);
+    init_global_state(cache, config, session_duration).unwrap();
 
     // 初始化模板緩存
     ssrkit::template::init_template_cache();
@@ -26,7 +26,7 @@ fn test_render() {
 
     let path = "/test";
     let params = HashMap::new();
-    let render_fn: Box<dyn FnOnce(&str) -> Result<String, String>> = Box::new(|props| {
+    let render_fn = |props: &str| {
         let json_props =
             serde_json::from_str::<serde_json::Value>(props).map_err(|e| e.to_string())?;
         let content = format!("test content with props: {}", json_props);
@@ -37,12 +37,11 @@ fn test_render() {
             "body": ""
         });
         Ok(result.to_string())
-    });
+    };
 
     let result = renderer.render(path, params, render_fn);
     if let Err(ref e) = result {
-        println!("Render error: {}", e);
-        assert!(false);
+        panic!("Render error: {}", e);
     }
     let (html, _) = result.unwrap();
     assert!(html.contains("test content with props:"));
@@ -59,7 +58,7 @@ fn test_render_with_island() {
     // 初始化全局狀態
     let cache = Cache::new(|config| config.get_global_state_cache_size());
     let session_duration = std::time::Duration::from_secs(3600);
-    init_global_state(cache, config, session_duration);
+    init_global_state(cache, config, session_duration).unwrap();
 
     // 初始化模板緩存
     ssrkit::template::init_template_cache();
@@ -71,7 +70,7 @@ fn test_render_with_island() {
 
     let path = "/test";
     let params = HashMap::new();
-    let render_fn: Box<dyn FnOnce(&str) -> Result<String, String>> = Box::new(|props| {
+    let render_fn = |props: &str| {
         let json_props =
             serde_json::from_str::<serde_json::Value>(props).map_err(|e| e.to_string())?;
         let content = format!("test content with props: {}", json_props);
@@ -82,14 +81,219 @@ fn test_render_with_island() {
             "body": ""
         });
         Ok(result.to_string())
-    });
+    };
 
     let processor = CombinedIslandProcessor::new();
     let result = renderer.render(path, params, render_fn, &processor);
     if let Err(ref e) = result {
-        println!("Render error: {}", e);
-        assert!(false);
+        panic!("Render error: {}", e);
     }
     let (html, _) = result.unwrap();
     assert!(html.contains("test content with props:"));
 }
+
+#[test]
+fn test_render_with_request_cookies() {
+    // 測試每個請求使用獨立的 Cookie 容器，只返回本次請求變更的 Cookie
+    let config = SsrkitConfig::default();
+    ssrkit::config::set_global_config(config.clone());
+    ssrkit::template::init_template_cache();
+
+    let renderer = SsrRenderer::new(
+        Box::new(CombinedParamsProcessor::new()),
+        #[cfg(feature = "island")]
+        Arc::new(IslandManager::new()),
+        Arc::new(Template::new()),
+    );
+    #[cfg(feature = "island")]
+    let processor = CombinedIslandProcessor::new();
+    let render_fn =
+        |_props: &str| Ok(serde_json::json!({ "html": "<div>cookies</div>" }).to_string());
+
+    let mut cookies = CookieManager::new();
+    cookies.add_original(Cookie::new("theme".to_string(), "dark".to_string()));
+    cookies.add(Cookie::new("token".to_string(), "user-a".to_string()));
+    let (_, set_cookies) = renderer
+        .render_with_cookies(
+            "/a",
+            HashMap::new(),
+            &mut cookies,
+            render_fn,
+            #[cfg(feature = "island")]
+            &processor,
+        )
+        .unwrap();
+    assert_eq!(set_cookies, vec!["token=user-a".to_string()]);
+    assert!(cookies.warnings().is_empty());
+
+    // 瀏覽器會忽略的 Cookie 照常輸出，警告留給調用者處理
+    let mut embed = Cookie::new("embed".to_string(), "v".to_string());
+    embed.same_site = Some(SameSite::None);
+    cookies.add(embed);
+    let (_, set_cookies) = renderer
+        .render_with_cookies(
+            "/a",
+            HashMap::new(),
+            &mut cookies,
+            render_fn,
+            #[cfg(feature = "island")]
+            &processor,
+        )
+        .unwrap();
+    assert_eq!(set_cookies.len(), 2);
+    assert_eq!(cookies.warnings().len(), 1);
+
+    let (_, set_cookies) = renderer
+        .render(
+            "/b",
+            HashMap::new(),
+            render_fn,
+            #[cfg(feature = "island")]
+            &processor,
+        )
+        .unwrap();
+    assert!(set_cookies.is_empty());
+}
+
+#[test]
+fn test_render_binds_session_cookie() {
+    // 測試渲染時自動載入 Session，並在首次寫入時創建 Session 和 Cookie
+    let config = SsrkitConfig::default();
+    ssrkit::config::set_global_config(config.clone());
+    let cache = Cache::new(|config| config.get_global_state_cache_size());
+    init_global_state(cache, config, std::time::Duration::from_secs(3600)).unwrap();
+    ssrkit::template::init_template_cache();
+
+    let renderer = SsrRenderer::new(
+        Box::new(CombinedParamsProcessor::new()),
+        #[cfg(feature = "island")]
+        Arc::new(IslandManager::new()),
+        Arc::new(Template::new()),
+    );
+    #[cfg(feature = "island")]
+    let processor = CombinedIslandProcessor::new();
+    let render_fn =
+        |_props: &str| Ok(serde_json::json!({ "html": "<div>session</div>" }).to_string());
+
+    // 沒有寫入時不會創建 Session
+    let mut cookies = CookieManager::new();
+    let mut session = RequestSession::new();
+    let (_, set_cookies) = renderer
+        .render_with_session(
+            "/",
+            HashMap::new(),
+            &mut cookies,
+            &mut session,
+            render_fn,
+            #[cfg(feature = "island")]
+            &processor,
+        )
+        .unwrap();
+    assert!(set_cookies.is_empty());
+    assert!(session.id().is_none());
+
+    session.get_mut().insert("visits", 1).unwrap();
+    let (_, set_cookies) = renderer
+        .render_with_session(
+            "/",
+            HashMap::new(),
+            &mut cookies,
+            &mut session,
+            render_fn,
+            #[cfg(feature = "island")]
+            &processor,
+        )
+        .unwrap();
+    let session_id = session.id().unwrap().to_string();
+    assert_eq!(set_cookies.len(), 1);
+    let cookie = Cookie::parse_set_cookie(&set_cookies[0]).unwrap();
+    assert_eq!(cookie.name, "ssrkit_session");
+    assert_eq!(cookie.value, session_id);
+    assert!(cookie.http_only);
+    assert_eq!(cookie.same_site, Some(SameSite::Lax));
+    assert_eq!(cookie.path.as_deref(), Some("/"));
+
+    // 下一個請求通過 Cookie 找回同一個 Session，且不再重複輸出 Cookie
+    let mut cookies = CookieManager::from_cookie_header(&format!("ssrkit_session={}", session_id));
+    let (_, set_cookies) = renderer
+        .render_with_cookies(
+            "/",
+            HashMap::new(),
+            &mut cookies,
+            render_fn,
+            #[cfg(feature = "island")]
+            &processor,
+        )
+        .unwrap();
+    assert!(set_cookies.is_empty());
+
+    let global_state = get_global_state().read().unwrap();
+    let mut manager = global_state.get_session_manager().write().unwrap();
+    let request_session = manager.load_request_session(&cookies);
+    assert_eq!(request_session.get().unwrap().get::<u32>("visits"), Some(1));
+}
+
+#[test]
+fn test_render_consumes_flash_messages() {
+    // 測試一次性提示注入到 props 中，且只在下一次渲染時出現一次
+    let config = SsrkitConfig::default();
+    ssrkit::config::set_global_config(config.clone());
+    let cache = Cache::new(|config| config.get_global_state_cache_size());
+    init_global_state(cache, config, std::time::Duration::from_secs(3600)).unwrap();
+    ssrkit::template::init_template_cache();
+
+    let renderer = SsrRenderer::new(
+        Box::new(CombinedParamsProcessor::new()),
+        #[cfg(feature = "island")]
+        Arc::new(IslandManager::new()),
+        Arc::new(Template::new()),
+    );
+    #[cfg(feature = "island")]
+    let processor = CombinedIslandProcessor::new();
+    let flash_of = |props: &str| {
+        let props = serde_json::from_str::<serde_json::Value>(props).unwrap();
+        serde_json::from_value::<Vec<FlashMessage>>(props["flash"].clone()).unwrap()
+    };
+
+    // 表單提交後寫入提示並保存 Session
+    let mut cookies = CookieManager::new();
+    let mut session = RequestSession::new();
+    session.get_mut().flash("success", "Saved");
+    {
+        let global_state = get_global_state().read().unwrap();
+        let mut manager = global_state.get_session_manager().write().unwrap();
+        manager
+            .commit_request_session(&mut session, &mut cookies)
+            .unwrap();
+    }
+    let session_id = session.id().unwrap().to_string();
+
+    let mut flashes = Vec::new();
+    for _ in 0..2 {
+        let mut cookies =
+            CookieManager::from_cookie_header(&format!("ssrkit_session={}", session_id));
+        renderer
+            .render_with_cookies(
+                "/",
+                HashMap::new(),
+                &mut cookies,
+                |props: &str| {
+                    flashes.push(flash_of(props));
+                    Ok(serde_json::json!({ "html": "<div>flash</div>" }).to_string())
+                },
+                #[cfg(feature = "island")]
+                &processor,
+            )
+            .unwrap();
+    }
+    assert_eq!(
+        flashes,
+        vec![
+            vec![FlashMessage {
+                level: "success".to_string(),
+                message: "Saved".to_string(),
+            }],
+            vec![],
+        ]
+    );
+}
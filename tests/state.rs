@@ -7,7 +7,7 @@ fn test_session_manager() {
     let config = SsrkitConfig::default();
     let cache = Cache::new(|config| config.get_global_state_cache_size());
     let session_duration = Duration::from_secs(3600);
-    init_global_state(cache, config, session_duration);
+    init_global_state(cache, config, session_duration).unwrap();
 
     let binding = get_global_state().read().unwrap();
     let session_manager = binding.get_session_manager();
@@ -22,3 +22,787 @@ fn test_session_manager() {
     assert!(session.is_some());
     assert_eq!(session.unwrap().user_id, user_id);
 }
+
+#[test]
+fn test_parse_cookie_header() {
+    // 測試解析請求的 Cookie 頭，包括引號、百分號編碼和重名 Cookie
+    let header = r#"token=abc; theme="dark%20mode"; token=shadowed; invalid; lang=zh-TW"#;
+    let cookies = Cookie::parse_cookie_header(header);
+    assert_eq!(cookies.len(), 4);
+    assert_eq!(cookies[1].value, "dark mode");
+
+    let manager = CookieManager::from_cookie_header(header);
+    assert_eq!(manager.get("token").unwrap().value, "abc");
+    assert_eq!(manager.get("lang").unwrap().value, "zh-TW");
+    assert!(manager.to_header_strings().is_empty());
+}
+
+#[test]
+fn test_set_cookie_round_trip() {
+    // 測試 Set-Cookie 字符串的往返
+    let mut cookie = Cookie::new("prefs".to_string(), "a b;c%".to_string());
+    cookie.path = Some("/".to_string());
+    cookie.domain = Some("example.com".to_string());
+    cookie.max_age = Some(chrono::Duration::seconds(60));
+    cookie.expires = Some(chrono::DateTime::from_timestamp(1_700_000_000, 0).unwrap());
+    cookie.secure = true;
+    cookie.http_only = true;
+    cookie.same_site = Some(SameSite::Lax);
+
+    let header = cookie.to_header_string();
+    assert!(header.starts_with("prefs=a%20b%3Bc%25;"));
+
+    let parsed = Cookie::parse_set_cookie(&header).unwrap();
+    assert_eq!(parsed.value, cookie.value);
+    assert_eq!(parsed.path, cookie.path);
+    assert_eq!(parsed.domain, cookie.domain);
+    assert_eq!(parsed.max_age, cookie.max_age);
+    assert_eq!(parsed.expires, cookie.expires);
+    assert!(parsed.secure && parsed.http_only);
+    assert_eq!(parsed.same_site, Some(SameSite::Lax));
+
+    assert!(Cookie::parse_set_cookie("no-equals-sign").is_err());
+}
+
+#[test]
+fn test_signed_cookies_with_key_rotation() {
+    // 測試簽名 Cookie 的驗證與密鑰輪換
+    let old_key = b"old-secret".to_vec();
+    let new_key = b"new-secret".to_vec();
+
+    let mut manager = CookieManager::new().with_signing_keys(vec![old_key.clone()]);
+    manager
+        .add_signed(Cookie::new("session".to_string(), "abc.123".to_string()))
+        .unwrap();
+    let header = manager.to_header_strings().join("; ");
+
+    // 輪換後舊簽名仍然有效
+    let rotated = CookieManager::from_cookie_header(&header)
+        .with_signing_keys(vec![new_key.clone(), old_key]);
+    assert_eq!(rotated.get_signed("session").unwrap().value, "abc.123");
+
+    // 移除舊密鑰後驗證失敗
+    let expired = CookieManager::from_cookie_header(&header).with_signing_keys(vec![new_key]);
+    assert!(expired.get_signed("session").is_none());
+
+    // 篡改的值驗證失敗
+    let tampered = header.replace("abc.123", "abc.124");
+    let tampered = CookieManager::from_cookie_header(&tampered)
+        .with_signing_keys(vec![b"old-secret".to_vec()]);
+    assert!(tampered.get_signed("session").is_none());
+
+    assert!(CookieManager::new()
+        .add_signed(Cookie::new("a".to_string(), "b".to_string()))
+        .is_err());
+
+    // 全局狀態創建的容器使用配置中的簽名密鑰
+    let state = GlobalState::new(
+        Cache::new(|config| config.get_global_state_cache_size()),
+        SsrkitConfig::change()
+            .cookie_signing_keys(vec![b"old-secret".to_vec()])
+            .finish(),
+        Duration::from_secs(60),
+    )
+    .unwrap();
+    let verified = state.cookie_manager().with_cookie_header(&header);
+    assert_eq!(verified.get_signed("session").unwrap().value, "abc.123");
+    let mut jar = state.cookie_manager();
+    jar.add_signed(Cookie::new("a".to_string(), "b".to_string()))
+        .unwrap();
+}
+
+#[test]
+fn test_private_cookies() {
+    // 測試加密 Cookie 的讀寫與密鑰輪換
+    let config = SsrkitConfig::change()
+        .cookie_encryption_keys(vec![b"first-secret".to_vec()])
+        .finish();
+
+    let mut jar = PrivateCookieManager::from_config(&config);
+    jar.add(Cookie::new("cart".to_string(), "item-1,item-2".to_string()));
+    assert_eq!(jar.get("cart").unwrap().value, "item-1,item-2");
+
+    let headers = jar.to_header_strings().unwrap();
+    assert_eq!(headers.len(), 1);
+    assert!(!headers[0].contains("item-1"));
+    let encrypted = Cookie::parse_set_cookie(&headers[0]).unwrap();
+    let request_header = format!("cart={}; other=plain", encrypted.value);
+
+    let rotated = PrivateCookieManager::new(vec![b"second".to_vec(), b"first-secret".to_vec()])
+        .with_cookie_header(&request_header);
+    assert_eq!(rotated.get("cart").unwrap().value, "item-1,item-2");
+    assert!(rotated.get("other").is_none());
+    assert!(rotated.to_header_strings().unwrap().is_empty());
+
+    // 值不能被挪用到其他名稱的 Cookie
+    let moved = PrivateCookieManager::new(vec![b"first-secret".to_vec()])
+        .with_cookie_header(&format!("profile={}", encrypted.value));
+    assert!(moved.get("profile").is_none());
+}
+
+#[test]
+fn test_cookie_deletion_emits_removal() {
+    // 測試刪除 Cookie 時輸出讓瀏覽器移除的 Set-Cookie
+    let mut manager = CookieManager::from_cookie_header("theme=dark; token=abc");
+    let mut token = Cookie::new("token".to_string(), "ignored".to_string());
+    token.path = Some("/app".to_string());
+    token.domain = Some("example.com".to_string());
+    manager.remove(token);
+    manager.delete("theme");
+    manager.delete("missing");
+    manager.add(Cookie::new("lang".to_string(), "en".to_string()));
+
+    assert!(manager.get("token").is_none());
+    assert_eq!(manager.removed().count(), 2);
+
+    let headers = manager.to_header_strings();
+    assert_eq!(headers.len(), 3);
+    let removal = headers
+        .iter()
+        .map(|h| Cookie::parse_set_cookie(h).unwrap())
+        .find(|c| c.name == "token")
+        .unwrap();
+    assert_eq!(removal.value, "");
+    assert_eq!(removal.max_age, Some(chrono::Duration::zero()));
+    assert!(removal.expires.unwrap() < chrono::Utc::now());
+    assert_eq!(removal.path.as_deref(), Some("/app"));
+    assert_eq!(removal.domain.as_deref(), Some("example.com"));
+
+    // 請求帶來的 Cookie 沒有 Path，刪除頭默認使用 Path=/ 才能移除瀏覽器中的 Cookie
+    let removal = headers
+        .iter()
+        .map(|h| Cookie::parse_set_cookie(h).unwrap())
+        .find(|c| c.name == "theme")
+        .unwrap();
+    assert_eq!(removal.path.as_deref(), Some("/"));
+    assert!(removal.domain.is_none());
+
+    // 重新設置後不再輸出刪除頭
+    manager.add(Cookie::new("theme".to_string(), "light".to_string()));
+    assert_eq!(manager.removed().count(), 1);
+}
+
+#[test]
+fn test_cookie_readd_after_delete_cancels_removal() {
+    // 測試刪除請求帶來的 Cookie 後再以 Path=/ 設置，只輸出新的 Cookie
+    let mut manager = CookieManager::from_cookie_header("theme=dark; ssrkit_session=old");
+    manager.delete("theme");
+    let mut theme = Cookie::new("theme".to_string(), "light".to_string());
+    theme.path = Some("/".to_string());
+    manager.add(theme);
+
+    let headers = manager.to_header_strings();
+    assert_eq!(headers.len(), 1);
+    let cookie = Cookie::parse_set_cookie(&headers[0]).unwrap();
+    assert_eq!(cookie.value, "light");
+    assert_eq!(cookie.path.as_deref(), Some("/"));
+
+    // 登出後在同一請求中創建新的 Session，新的 Session Cookie 不能被刪除頭覆蓋
+    manager.delete("ssrkit_session");
+    let mut session = Cookie::new("ssrkit_session".to_string(), "new".to_string());
+    session.path = Some("/".to_string());
+    session.http_only = true;
+    manager.add(session);
+    assert_eq!(manager.removed().count(), 0);
+    let sessions: Vec<Cookie> = manager
+        .to_header_strings()
+        .iter()
+        .map(|h| Cookie::parse_set_cookie(h).unwrap())
+        .filter(|c| c.name == "ssrkit_session")
+        .collect();
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0].value, "new");
+
+    // 不同 Path 的 Cookie 不會撤銷 Path=/ 的刪除
+    manager.delete("theme");
+    let mut scoped = Cookie::new("theme".to_string(), "blue".to_string());
+    scoped.path = Some("/app".to_string());
+    manager.add(scoped);
+    assert_eq!(manager.removed().count(), 1);
+}
+
+#[test]
+fn test_cookie_attribute_validation() {
+    // 測試 Cookie 名稱、前綴規則、Partitioned 和大小限制的校驗
+    assert!(Cookie::new("bad name".to_string(), "v".to_string())
+        .validate()
+        .is_err());
+
+    let mut host = Cookie::new("__Host-id".to_string(), "v".to_string());
+    assert!(host.validate().is_err());
+    host.secure = true;
+    host.path = Some("/".to_string());
+    assert!(host.validate().is_ok());
+    host.domain = Some("example.com".to_string());
+    assert!(host.validate().is_err());
+
+    let mut secure = Cookie::new("__Secure-id".to_string(), "v".to_string());
+    assert!(secure.validate().is_err());
+    secure.secure = true;
+    secure.partitioned = true;
+    assert!(secure.validate().is_ok());
+    assert!(secure.to_header_string().ends_with("; Secure; Partitioned"));
+
+    let mut cross_site = Cookie::new("embed".to_string(), "v".to_string());
+    cross_site.same_site = Some(SameSite::None);
+    assert_eq!(cross_site.warnings().len(), 1);
+    cross_site.secure = true;
+    assert!(cross_site.warnings().is_empty());
+
+    let mut injected = Cookie::new("id".to_string(), "v".to_string());
+    injected.path = Some("/; Domain=evil.com".to_string());
+    assert!(injected.validate().is_err());
+    injected.path = None;
+    injected.domain = Some("example.com\r\nX-Injected: 1".to_string());
+    assert!(injected.validate().is_err());
+
+    let mut manager = CookieManager::new();
+    assert!(manager
+        .try_add(Cookie::new("big".to_string(), "x".repeat(4096)))
+        .is_err());
+    assert_eq!(manager.try_add(cross_site.clone()).unwrap().len(), 0);
+    cross_site.secure = false;
+    assert_eq!(manager.try_add(cross_site).unwrap().len(), 1);
+    assert_eq!(manager.warnings().len(), 1);
+    manager.add(Cookie::new("__Secure-a".to_string(), "v".to_string()));
+    assert!(manager.validate().is_err());
+
+    // 刪除請求帶來的帶前綴 Cookie 時，刪除頭滿足前綴的要求
+    let mut manager = CookieManager::from_cookie_header("__Host-sid=abc; __Secure-pref=1");
+    manager.delete("__Host-sid");
+    manager.delete("__Secure-pref");
+    assert!(manager.validate().is_ok());
+    let removals: Vec<Cookie> = manager
+        .to_header_strings()
+        .iter()
+        .map(|h| Cookie::parse_set_cookie(h).unwrap())
+        .collect();
+    assert_eq!(removals.len(), 2);
+    assert!(removals.iter().all(|c| c.secure));
+    let host = removals.iter().find(|c| c.name == "__Host-sid").unwrap();
+    assert_eq!(host.path.as_deref(), Some("/"));
+    assert!(host.domain.is_none());
+
+    assert!(Cookie::parse_set_cookie("a=b; SameSite=Sometimes").is_err());
+    assert!(Cookie::parse_set_cookie("a=b c").is_err());
+}
+
+#[test]
+fn test_cookies_keyed_by_name_domain_and_path() {
+    // 測試同名但 Domain/Path 不同的 Cookie 互不覆蓋
+    let scoped = |path: &str, domain: Option<&str>, value: &str| {
+        let mut cookie = Cookie::new("token".to_string(), value.to_string());
+        cookie.path = Some(path.to_string());
+        cookie.domain = domain.map(str::to_string);
+        cookie
+    };
+
+    let mut manager = CookieManager::new();
+    manager.add(scoped("/", None, "root"));
+    manager.add(scoped("/admin", None, "admin"));
+    manager.add(scoped("/", Some("api.example.com"), "api"));
+    assert_eq!(manager.get_all("token").len(), 3);
+    assert_eq!(manager.to_header_strings().len(), 3);
+
+    assert_eq!(
+        manager.get_scoped("token", None, Some("/")).unwrap().value,
+        "root"
+    );
+    assert_eq!(
+        manager
+            .get_scoped("token", Some(".API.example.com"), Some("/"))
+            .unwrap()
+            .value,
+        "api"
+    );
+    assert_eq!(
+        manager
+            .get_matching("token", "www.example.com", "/admin/users")
+            .unwrap()
+            .value,
+        "admin"
+    );
+    assert_eq!(
+        manager
+            .get_matching("token", "www.example.com", "/administrator")
+            .unwrap()
+            .value,
+        "root"
+    );
+
+    manager.delete_scoped("token", None, Some("/admin"));
+    assert_eq!(manager.get_all("token").len(), 2);
+    assert_eq!(manager.removed().count(), 1);
+
+    manager.delete("token");
+    assert!(manager.get("token").is_none());
+    assert_eq!(manager.removed().count(), 3);
+}
+
+#[test]
+fn test_file_session_store_survives_restart() {
+    // 測試文件存儲的 Session 在重新創建 SessionManager 後仍然可用
+    let dir = std::env::temp_dir().join(format!("ssrkit-sessions-{}", std::process::id()));
+    let config = std::sync::Arc::new(
+        SsrkitConfig::change()
+            .session_store(SessionStoreKind::File(dir.clone()))
+            .finish(),
+    );
+
+    let session_id = {
+        let mut manager = SessionManager::new(Duration::from_secs(60), config.clone()).unwrap();
+        let session_id = manager.create_session("alice".to_string());
+        let mut session = manager.get_session(&session_id).unwrap();
+        session.insert("theme", "dark").unwrap();
+        session.insert("visits", 3).unwrap();
+        session_id
+    };
+
+    let mut manager = SessionManager::new(Duration::from_secs(60), config).unwrap();
+    {
+        let session = manager.get_session(&session_id).unwrap();
+        assert_eq!(session.user_id, "alice");
+        assert_eq!(session.get::<String>("theme").as_deref(), Some("dark"));
+        assert_eq!(session.get::<u32>("visits"), Some(3));
+    }
+    assert!(manager.get_session("../escape").is_none());
+
+    // 損壞的文件不會中斷遍歷，並被清理
+    let corrupt = dir.join("corrupt.json");
+    std::fs::write(&corrupt, "not json").unwrap();
+    let store = FileSessionStore::new(dir.clone()).unwrap();
+    let mut visited = Vec::new();
+    store
+        .retain(&mut |id, _| {
+            visited.push(id.to_string());
+            true
+        })
+        .unwrap();
+    assert_eq!(visited, vec![session_id.clone()]);
+    assert!(!corrupt.exists());
+
+    manager.remove_session(&session_id);
+    assert!(manager.get_session(&session_id).is_none());
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn test_file_session_store_custom_alphabet() {
+    // 測試自定義字母表生成的 Session ID 編碼為文件名，重啟後仍能按用戶查找
+    let dir = std::env::temp_dir().join(format!("ssrkit-alphabet-{}", std::process::id()));
+    let config = std::sync::Arc::new(
+        SsrkitConfig::change()
+            .nanoid_alphabet(vec!['a', 'b', 'c', '.', '~'])
+            .session_store(SessionStoreKind::File(dir.clone()))
+            .finish(),
+    );
+
+    let session_id = {
+        let mut manager = SessionManager::new(Duration::from_secs(60), config.clone()).unwrap();
+        manager.create_session("alice".to_string())
+    };
+    for entry in std::fs::read_dir(&dir).unwrap() {
+        let name = entry.unwrap().file_name().into_string().unwrap();
+        let stem = name.strip_suffix(".json").unwrap();
+        assert!(stem
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '%' || c == '-' || c == '_'));
+    }
+
+    let mut manager = SessionManager::new(Duration::from_secs(60), config).unwrap();
+    let sessions = manager.list_user_sessions("alice");
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0].0, session_id);
+    manager.remove_session(&session_id);
+    assert!(manager.get_session(&session_id).is_none());
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn test_session_store_creation_error() {
+    // 測試 Session 目錄無法創建時返回錯誤而不是 panic
+    let file = std::env::temp_dir().join(format!("ssrkit-not-a-dir-{}", std::process::id()));
+    std::fs::write(&file, "").unwrap();
+    let config = SsrkitConfig::change()
+        .session_store(SessionStoreKind::File(file.join("sessions")))
+        .finish();
+
+    assert!(
+        SessionManager::new(Duration::from_secs(60), std::sync::Arc::new(config.clone())).is_err()
+    );
+    let state = GlobalState::new(
+        Cache::new(|config| config.get_global_state_cache_size()),
+        config,
+        Duration::from_secs(60),
+    );
+    assert!(state.is_err());
+    let _ = std::fs::remove_file(file);
+}
+
+#[test]
+fn test_regenerate_session_id() {
+    // 測試重新生成 Session ID 後舊 ID 失效，且 Cookie 同步更新
+    let config = std::sync::Arc::new(SsrkitConfig::default());
+    let mut manager = SessionManager::new(Duration::from_secs(60), config).unwrap();
+    let old_id = manager.create_session("bob".to_string());
+    manager
+        .get_session(&old_id)
+        .unwrap()
+        .insert("role", "admin")
+        .unwrap();
+
+    let mut cookies = CookieManager::from_cookie_header(&format!("sid={}", old_id));
+    let new_id = manager
+        .regenerate_session_cookie(&mut cookies, "sid")
+        .unwrap();
+    assert_ne!(new_id, old_id);
+    assert!(manager.get_session(&old_id).is_none());
+    let session = manager.get_session(&new_id).unwrap();
+    assert_eq!(session.user_id, "bob");
+    assert_eq!(session.get::<String>("role").as_deref(), Some("admin"));
+    drop(session);
+
+    let headers = cookies.to_header_strings();
+    assert_eq!(headers.len(), 1);
+    let cookie = Cookie::parse_set_cookie(&headers[0]).unwrap();
+    assert_eq!((cookie.name.as_str(), cookie.value), ("sid", new_id));
+    assert!(cookie.http_only);
+    assert!(manager.regenerate_session(&old_id).is_none());
+}
+
+#[test]
+fn test_regenerate_request_session_on_login() {
+    // 測試登錄時在請求中重新生成 Session 後繼續寫入，提交時不會恢復舊 ID
+    let config = std::sync::Arc::new(SsrkitConfig::default());
+    let mut manager = SessionManager::new(Duration::from_secs(60), config).unwrap();
+
+    // 匿名訪問時已建立 Session
+    let mut cookies = CookieManager::new();
+    let mut request_session = RequestSession::new();
+    request_session
+        .get_mut()
+        .insert("cart", vec!["pen"])
+        .unwrap();
+    manager
+        .commit_request_session(&mut request_session, &mut cookies)
+        .unwrap();
+    let old_id = request_session.id().unwrap().to_string();
+
+    // 登錄請求
+    let mut cookies = CookieManager::from_cookie_header(&format!("ssrkit_session={}", old_id));
+    let mut request_session = manager.load_request_session(&cookies);
+    let token = manager.csrf_token(&mut request_session);
+    let new_id = manager
+        .regenerate_request_session(&mut request_session, &mut cookies)
+        .unwrap();
+    assert_ne!(new_id, old_id);
+    assert_eq!(request_session.id(), Some(new_id.as_str()));
+    assert!(!request_session.verify_csrf_token(&token));
+
+    request_session.get_mut().user_id = "mallory".to_string();
+    manager
+        .commit_request_session(&mut request_session, &mut cookies)
+        .unwrap();
+
+    assert!(manager.get_session(&old_id).is_none());
+    let session = manager.get_session(&new_id).unwrap();
+    assert_eq!(session.user_id, "mallory");
+    assert_eq!(
+        session.get::<Vec<String>>("cart"),
+        Some(vec!["pen".to_string()])
+    );
+    drop(session);
+    assert_eq!(manager.list_user_sessions("mallory").len(), 1);
+
+    let headers = cookies.to_header_strings();
+    assert_eq!(headers.len(), 1);
+    let cookie = Cookie::parse_set_cookie(&headers[0]).unwrap();
+    assert_eq!(cookie.value, new_id);
+
+    // 沒有已保存的 Session 時無需重新生成
+    let mut request_session = RequestSession::new();
+    assert!(manager
+        .regenerate_request_session(&mut request_session, &mut CookieManager::new())
+        .is_none());
+}
+
+#[test]
+fn test_commit_does_not_resurrect_revoked_session() {
+    // 測試請求處理期間 Session 被撤銷或重新生成後，提交不會重新寫入舊 ID
+    let config = std::sync::Arc::new(SsrkitConfig::default());
+    let mut manager = SessionManager::new(Duration::from_secs(60), config).unwrap();
+    let session_id = manager.create_session("alice".to_string());
+
+    let mut cookies = CookieManager::from_cookie_header(&format!("ssrkit_session={}", session_id));
+    let mut request_session = manager.load_request_session(&cookies);
+    assert_eq!(manager.revoke_user_sessions("alice"), 1);
+    request_session.get_mut().flash("info", "saved");
+    manager
+        .commit_request_session(&mut request_session, &mut cookies)
+        .unwrap();
+    assert!(manager.get_session(&session_id).is_none());
+    assert!(manager.list_user_sessions("alice").is_empty());
+    assert!(request_session.id().is_none());
+    assert!(cookies.to_header_strings().is_empty());
+
+    // 另一個請求已重新生成 Session ID
+    let session_id = manager.create_session("bob".to_string());
+    let mut cookies = CookieManager::from_cookie_header(&format!("ssrkit_session={}", session_id));
+    let mut request_session = manager.load_request_session(&cookies);
+    let new_id = manager.regenerate_session(&session_id).unwrap();
+    request_session.get_mut().flash("info", "saved");
+    manager
+        .commit_request_session(&mut request_session, &mut cookies)
+        .unwrap();
+    assert!(manager.get_session(&session_id).is_none());
+    assert!(!manager.get_session(&new_id).unwrap().has_flashes());
+}
+
+#[test]
+fn test_typed_session_data() {
+    // 測試 Session 中保存任意可序列化的值
+    #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+    struct Cart {
+        items: Vec<String>,
+        total: f64,
+    }
+
+    let mut session = Session::new("carol".to_string());
+    let cart = Cart {
+        items: vec!["book".to_string()],
+        total: 12.5,
+    };
+    session.insert("cart", &cart).unwrap();
+    session.insert("admin", true).unwrap();
+
+    assert_eq!(session.get::<Cart>("cart"), Some(cart));
+    assert_eq!(session.get::<bool>("admin"), Some(true));
+    assert_eq!(session.get::<u32>("admin"), None);
+    assert!(session.remove("admin").is_some());
+    assert!(session.get::<bool>("admin").is_none());
+}
+
+#[test]
+fn test_session_reaper_and_absolute_timeout() {
+    // 測試後台清理線程刪除閒置過期的 Session，以及絕對超時不受訪問續期影響
+    let dir = std::env::temp_dir().join(format!("ssrkit-reaper-{}", std::process::id()));
+    let config = std::sync::Arc::new(
+        SsrkitConfig::change()
+            .session_store(SessionStoreKind::File(dir.clone()))
+            .finish(),
+    );
+    let manager = std::sync::Arc::new(std::sync::RwLock::new(
+        SessionManager::new(Duration::from_millis(50), config).unwrap(),
+    ));
+    manager.write().unwrap().create_session("dave".to_string());
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+
+    // 清理線程使用真實時間，輪詢等待而不是依賴固定的時間窗口
+    let mut reaper = SessionReaper::start(&manager, Duration::from_millis(20));
+    let deadline = std::time::Instant::now() + Duration::from_secs(5);
+    while std::fs::read_dir(&dir).unwrap().count() > 0 && std::time::Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+    reaper.stop();
+    let _ = std::fs::remove_dir_all(dir);
+
+    let clock = std::sync::Arc::new(ManualClock::default());
+    let config = std::sync::Arc::new(
+        SsrkitConfig::change()
+            .session_absolute_timeout(Duration::from_secs(100))
+            .finish(),
+    );
+    let mut manager = SessionManager::new(Duration::from_secs(60), config)
+        .unwrap()
+        .with_clock(clock.clone());
+    let session_id = manager.create_session("erin".to_string());
+    for _ in 0..3 {
+        clock.advance(Duration::from_secs(30));
+        assert!(manager.get_session(&session_id).is_some());
+    }
+    clock.advance(Duration::from_secs(11));
+    assert!(manager.get_session(&session_id).is_none());
+}
+
+#[test]
+fn test_user_session_index() {
+    // 測試按用戶列出、撤銷 Session，以及每個用戶的 Session 數量上限
+    let config = std::sync::Arc::new(SsrkitConfig::change().session_max_per_user(2).finish());
+    let mut manager = SessionManager::new(Duration::from_secs(60), config).unwrap();
+    let first = manager.create_session("frank".to_string());
+    let second = manager.create_session("frank".to_string());
+    let other = manager.create_session("grace".to_string());
+    let second = manager.regenerate_session(&second).unwrap();
+    let third = manager.create_session("frank".to_string());
+
+    let ids: Vec<String> = manager
+        .list_user_sessions("frank")
+        .into_iter()
+        .map(|(id, _)| id)
+        .collect();
+    assert_eq!(ids, vec![second, third]);
+    assert!(manager.get_session(&first).is_none());
+
+    assert_eq!(manager.revoke_user_sessions("frank"), 2);
+    assert!(manager.list_user_sessions("frank").is_empty());
+    assert_eq!(manager.list_user_sessions("grace").len(), 1);
+    assert!(manager.get_session(&other).is_some());
+}
+
+#[test]
+fn test_session_lifecycle_hooks() {
+    // 測試創建、重新生成、過期和移除 Session 時觸發鉤子
+    let clock = std::sync::Arc::new(ManualClock::default());
+    let config = std::sync::Arc::new(SsrkitConfig::default());
+    let mut manager = SessionManager::new(Duration::from_secs(60), config)
+        .unwrap()
+        .with_clock(clock.clone());
+    let events = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let recorded = events.clone();
+    manager.on_event(move |event| {
+        let name = match event {
+            SessionEvent::Created { session, .. } => format!("created:{}", session.user_id),
+            SessionEvent::Touched { .. } => "touched".to_string(),
+            SessionEvent::Regenerated { .. } => "regenerated".to_string(),
+            SessionEvent::Expired { .. } => "expired".to_string(),
+            SessionEvent::Destroyed { session, .. } => format!("destroyed:{}", session.user_id),
+        };
+        recorded.lock().unwrap().push(name);
+    });
+
+    let first = manager.create_session("heidi".to_string());
+    assert!(manager.get_session(&first).is_some());
+    let first = manager.regenerate_session(&first).unwrap();
+    manager.remove_session(&first);
+
+    let second = manager.create_session("ivan".to_string());
+    let third = manager.create_session("judy".to_string());
+    clock.advance(Duration::from_secs(61));
+    assert!(manager.get_session(&second).is_none());
+    manager.cleanup_expired_sessions();
+    assert!(manager.get_session(&third).is_none());
+
+    assert_eq!(
+        *events.lock().unwrap(),
+        vec![
+            "created:heidi",
+            "touched",
+            "touched",
+            "regenerated",
+            "destroyed:heidi",
+            "created:ivan",
+            "created:judy",
+            "expired",
+            "expired",
+        ]
+    );
+}
+
+#[test]
+fn test_csrf_token() {
+    // 測試 CSRF 令牌按配置的字母表生成、在同一 Session 中保持不變，並在重新生成 Session 後輪換
+    let config = std::sync::Arc::new(
+        SsrkitConfig::change()
+            .nanoid_alphabet("abc".chars().collect())
+            .finish(),
+    );
+    let mut manager = SessionManager::new(Duration::from_secs(60), config).unwrap();
+    let mut request_session = RequestSession::new();
+    assert!(!request_session.verify_csrf_token(""));
+
+    let token = manager.csrf_token(&mut request_session);
+    assert!(token.chars().all(|c| "abc".contains(c)));
+    assert_eq!(manager.csrf_token(&mut request_session), token);
+    assert!(request_session.verify_csrf_token(&token));
+    assert!(!request_session.verify_csrf_token(&token[1..]));
+    assert!(!request_session.verify_csrf_token(""));
+
+    let mut cookies = CookieManager::new();
+    manager
+        .commit_request_session(&mut request_session, &mut cookies)
+        .unwrap();
+    let session_id = request_session.id().unwrap().to_string();
+    let new_session_id = manager.regenerate_session(&session_id).unwrap();
+    let session = manager.get_session(&new_session_id).unwrap();
+    assert!(session.csrf_token().is_none());
+    assert!(!session.verify_csrf_token(&token));
+}
+
+#[test]
+fn test_session_snapshot_survives_restart() {
+    // 測試 Session 快照在新的 SessionManager 中恢復，並保留牆上時間戳和用戶索引
+    let path = std::env::temp_dir().join(format!("ssrkit-snapshot-{}.json", std::process::id()));
+    let config = std::sync::Arc::new(SsrkitConfig::default());
+
+    let mut manager = SessionManager::new(Duration::from_secs(60), config.clone()).unwrap();
+    let session_id = manager.create_session("kate".to_string());
+    let created_at = {
+        let mut session = manager.get_session(&session_id).unwrap();
+        session.insert("cart", vec!["pen"]).unwrap();
+        session.created_at
+    };
+    manager.save_snapshot(&path).unwrap();
+
+    let mut restarted = SessionManager::new(Duration::from_secs(60), config).unwrap();
+    assert_eq!(restarted.load_snapshot(&path).unwrap(), 1);
+    let sessions = restarted.list_user_sessions("kate");
+    assert_eq!(sessions.len(), 1);
+    let (restored_id, session) = &sessions[0];
+    assert_eq!(restored_id, &session_id);
+    assert_eq!(session.created_at, created_at);
+    assert_eq!(
+        session.get::<Vec<String>>("cart"),
+        Some(vec!["pen".to_string()])
+    );
+    let _ = std::fs::remove_file(path);
+
+    // Cookie 同樣可以序列化
+    let mut cookie = Cookie::new("theme".to_string(), "dark".to_string());
+    cookie.max_age = Some(chrono::Duration::seconds(60));
+    cookie.same_site = Some(SameSite::Strict);
+    let json = serde_json::to_string(&cookie).unwrap();
+    let restored: Cookie = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored.to_header_string(), cookie.to_header_string());
+}
+
+#[test]
+fn test_manual_clock_drives_expiry() {
+    // 測試使用手動時鐘驗證 Session 和 Cookie 的過期時間，無需等待
+    let clock = std::sync::Arc::new(ManualClock::default());
+    let state = GlobalState::with_clock(
+        Cache::new(|config| config.get_global_state_cache_size()),
+        SsrkitConfig::change()
+            .session_absolute_timeout(Duration::from_secs(600))
+            .finish(),
+        Duration::from_secs(60),
+        clock.clone(),
+    )
+    .unwrap();
+    let mut manager = state.get_session_manager().write().unwrap();
+    let session_id = manager.create_session("leo".to_string());
+    assert_eq!(
+        manager.get_session(&session_id).unwrap().created_at,
+        clock.now()
+    );
+
+    for _ in 0..10 {
+        clock.advance(Duration::from_secs(59));
+        assert!(manager.get_session(&session_id).is_some());
+    }
+    clock.advance(Duration::from_secs(59));
+    assert!(manager.get_session(&session_id).is_none());
+
+    let session_id = manager.create_session("leo".to_string());
+    clock.advance(Duration::from_secs(60));
+    assert!(manager.get_session(&session_id).is_none());
+
+    let mut cookies = state.cookie_manager();
+    cookies.add(Cookie::new("remember".to_string(), "1".to_string()));
+    cookies.refresh("remember");
+    assert_eq!(
+        cookies.get("remember").unwrap().expires,
+        Some(clock.now() + chrono::Duration::days(30))
+    );
+}